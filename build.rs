@@ -1,12 +1,35 @@
 fn main() {
+    println!("cargo:rerun-if-changed=src/stub.h");
+
     // NOTE: this is a hack to allow this crate to build on docs.rs.
     //       https://github.com/sonodima/microseh/pull/11#issuecomment-2385633164
-    if std::env::var_os("CARGO_CFG_DOCSRS").is_some()
-        || std::env::var_os("CARGO_CFG_WINDOWS").is_none()
-    {
+    if std::env::var_os("CARGO_CFG_DOCSRS").is_some() {
+        println!("cargo:warning=building for a non-supported platform, exception handling will not be available");
+        return;
+    }
+
+    let stub = if std::env::var_os("CARGO_CFG_WINDOWS").is_some() {
+        "src/stub.c"
+    } else if std::env::var_os("CARGO_CFG_UNIX").is_some() {
+        "src/stub_posix.c"
+    } else {
         println!("cargo:warning=building for a non-supported platform, exception handling will not be available");
         return;
+    };
+
+    println!("cargo:rerun-if-changed={}", stub);
+    println!("cargo:rerun-if-changed=src/memory.c");
+    println!("cargo:rerun-if-changed=src/stub_user.c");
+
+    let mut build = cc::Build::new();
+    build.file(stub).file("src/memory.c");
+
+    // Kernel drivers are built without `std`, and cannot link against the user-mode libraries.
+    if std::env::var_os("CARGO_CFG_WINDOWS").is_some()
+        && std::env::var_os("CARGO_FEATURE_STD").is_some()
+    {
+        build.file("src/stub_user.c").define("MS_USER_MODE", None);
     }
 
-    cc::Build::new().file("src/stub.c").compile("sehstub");
+    build.compile("sehstub");
 }
@@ -18,6 +18,6 @@ fn with_propagation() -> Result<(), microseh::Exception> {
 
 fn main() {
     if let Err(ex) = with_propagation() {
-        println!("{:?}: {}", ex.address(), ex);
+        println!("{:#x}: {}", ex.address(), ex);
     }
 }
@@ -30,8 +30,16 @@ fn main() {
     //     println!("data: {}", res.data);
     //     INVALID_PTR.read_volatile();
     // });
+    //
+    // Or, access the memory within `microseh::unwind_on_fault`, inside of
+    // `microseh::try_seh_cleanup`, which drops the resource before returning the exception:
+    // let ex = microseh::try_seh_cleanup(|| {
+    //     let res = Resource::new();
+    //     println!("data: {}", res.data);
+    //     microseh::unwind_on_fault(|| unsafe { INVALID_PTR.read_volatile() });
+    // });
 
     if let Err(ex) = ex {
-        println!("{:?}: {}", ex.address(), ex);
+        println!("{:#x}: {}", ex.address(), ex);
     }
 }
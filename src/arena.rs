@@ -0,0 +1,218 @@
+use core::{
+    alloc::Layout,
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+    ptr::NonNull,
+};
+
+/// Size of the first chunk of memory allocated by an arena.
+const INITIAL_CHUNK_SIZE: usize = 0x1000;
+/// Size above which the chunks of memory allocated by an arena stop doubling.
+const MAXIMUM_CHUNK_SIZE: usize = 0x10_0000;
+
+/// Bump allocator whose allocations are all released at once, when the guarded region that\
+/// owns it returns.
+///
+/// Allocating from the arena only moves a pointer within the current chunk of memory, so\
+/// nothing is left to clean up if an exception interrupts the code that uses the allocations.
+///
+/// The destructors of the values stored in the arena are never run, so they should not own\
+/// other resources, such as heap allocations that are not made through the arena.
+pub struct Arena {
+    chunks: UnsafeCell<Vec<Box<[MaybeUninit<u8>]>>>,
+    position: Cell<usize>,
+    end: Cell<usize>,
+    allocated: Cell<usize>,
+}
+
+impl Arena {
+    /// Creates a new arena, which allocates no memory until it is first used.
+    pub(crate) fn new() -> Self {
+        Self {
+            chunks: UnsafeCell::new(Vec::new()),
+            position: Cell::new(0),
+            end: Cell::new(0),
+            allocated: Cell::new(0),
+        }
+    }
+
+    /// Allocates a block of memory with the given layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The size and alignment of the block.
+    ///
+    /// # Returns
+    ///
+    /// A pointer to the uninitialized block, which is valid until the arena is dropped.
+    pub fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // SAFETY: The alignment of a layout is never zero.
+            return unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+        }
+
+        let start = match self.fit(layout) {
+            Some(start) => start,
+            None => {
+                self.grow(layout);
+                self.fit(layout).expect("the new chunk fits the layout")
+            }
+        };
+
+        self.position.set(start + layout.size());
+        self.allocated.set(self.allocated.get() + layout.size());
+
+        // SAFETY: The block lies within a chunk, whose address is never zero.
+        unsafe { NonNull::new_unchecked(start as *mut u8) }
+    }
+
+    /// Moves the value into the arena.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to store.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the stored value, whose destructor will not run.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+
+        // SAFETY: The block is properly sized and aligned for `T`, and is not used by any
+        //         other allocation.
+        unsafe {
+            ptr.as_ptr().write(value);
+            &mut *ptr.as_ptr()
+        }
+    }
+
+    /// Copies the slice into the arena.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - The values to copy.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the copy of the slice.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        let layout = Layout::for_value(values);
+        let ptr = self.alloc_layout(layout).cast::<T>();
+
+        // SAFETY: The block is properly sized and aligned for the slice, and is not used by
+        //         any other allocation.
+        unsafe {
+            ptr.as_ptr()
+                .copy_from_nonoverlapping(values.as_ptr(), values.len());
+            core::slice::from_raw_parts_mut(ptr.as_ptr(), values.len())
+        }
+    }
+
+    /// Copies the string into the arena.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The string to copy.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the copy of the string.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, value: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(value.as_bytes());
+
+        // SAFETY: The bytes were copied from a valid string.
+        unsafe { core::str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// # Returns
+    ///
+    /// The number of bytes that were allocated from the arena, not counting padding.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// # Returns
+    ///
+    /// The address of a block with the given layout within the current chunk, if it fits.
+    fn fit(&self, layout: Layout) -> Option<usize> {
+        let start = self
+            .position
+            .get()
+            .checked_next_multiple_of(layout.align())?;
+        let end = start.checked_add(layout.size())?;
+
+        match end <= self.end.get() && self.end.get() != 0 {
+            true => Some(start),
+            false => None,
+        }
+    }
+
+    /// Allocates a new chunk of memory that fits the given layout, and makes it current.
+    fn grow(&self, layout: Layout) {
+        // SAFETY: The arena is not shared between threads, and no reference to the list of
+        //         chunks outlives this function. The chunks themselves never move.
+        let chunks = unsafe { &mut *self.chunks.get() };
+
+        let size = chunks
+            .last()
+            .map_or(INITIAL_CHUNK_SIZE, |chunk| {
+                (chunk.len() * 2).min(MAXIMUM_CHUNK_SIZE)
+            })
+            .max(layout.size() + layout.align());
+
+        let chunk = vec![MaybeUninit::<u8>::uninit(); size].into_boxed_slice();
+        let start = chunk.as_ptr() as usize;
+        chunks.push(chunk);
+
+        self.position.set(start);
+        self.end.set(start + size);
+    }
+}
+
+impl core::fmt::Debug for Arena {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Arena")
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment() {
+        let arena = Arena::new();
+        arena.alloc(1u8);
+
+        let value = arena.alloc(0x1337u64);
+        assert_eq!(value as *mut u64 as usize % core::mem::align_of::<u64>(), 0);
+        assert_eq!(*value, 0x1337);
+        assert_eq!(arena.allocated_bytes(), 9);
+    }
+
+    #[test]
+    fn growth() {
+        let arena = Arena::new();
+        let first = arena.alloc_slice_copy(&[0xAAu8; INITIAL_CHUNK_SIZE - 1]);
+        let second = arena.alloc_slice_copy(&[0xBBu8; INITIAL_CHUNK_SIZE * 4]);
+
+        assert!(first.iter().all(|&byte| byte == 0xAA));
+        assert!(second.iter().all(|&byte| byte == 0xBB));
+        assert_eq!(arena.allocated_bytes(), INITIAL_CHUNK_SIZE * 5 - 1);
+    }
+
+    #[test]
+    fn strings() {
+        let arena = Arena::new();
+        let value = arena.alloc_str("microseh");
+        value.make_ascii_uppercase();
+
+        assert_eq!(value, "MICROSEH");
+        assert_eq!(arena.alloc_slice_copy::<u32>(&[]), &[]);
+    }
+}
@@ -1,36 +1,154 @@
-/// Represents a system-specific exception code.
-///
-/// This enum encapsulates the various exception codes that can be returned by the
-/// `GetExceptionCode` Windows API function.
-///
-/// See: <https://learn.microsoft.com/en-us/windows/win32/debug/getexceptioncode>
-#[repr(u32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum ExceptionCode {
-    Invalid = 0x0,
-    AccessViolation = 0xC0000005,
-    ArrayBoundsExceeded = 0xC000008C,
-    Breakpoint = 0x80000003,
-    DataTypeMisalignment = 0x80000002,
-    FltDenormalOperand = 0xC000008D,
-    FltDivideByZero = 0xC000008E,
-    FltInexactResult = 0xC000008F,
-    FltInvalidOperation = 0xC0000090,
-    FltOverflow = 0xC0000091,
-    FltStackCheck = 0xC0000092,
-    FltUnderflow = 0xC0000093,
-    GuardPage = 0x80000001,
-    IllegalInstruction = 0xC000001D,
-    InPageError = 0xC0000006,
-    IntDivideByZero = 0xC0000094,
-    IntOverflow = 0xC0000095,
-    InvalidDisposition = 0xC0000026,
-    InvalidHandle = 0xC0000008,
-    NonContinuableException = 0xC0000025,
-    PrivilegedInstruction = 0xC0000096,
-    SingleStep = 0x80000004,
-    StackOverflow = 0xC00000FD,
-    UnwindConsolidate = 0x80000029,
+/// Generates the `ExceptionCode` enum and its conversions from a table of known codes.
+macro_rules! exception_codes {
+    ($($name:ident = $value:literal => $description:literal,)*) => {
+        /// Represents a system-specific exception code.
+        ///
+        /// This enum encapsulates the various exception codes that can be returned by the
+        /// `GetExceptionCode` Windows API function. Codes that are not known to this library
+        /// are preserved in the `Other` variant, so no information is lost in the conversion.
+        ///
+        /// Codes should be built with `ExceptionCode::from` or `ExceptionCode::from_raw`, which\
+        /// never put a known code in `Other`. A known code built as `Other` by hand compares equal\
+        /// to its variant, but is not matched by a pattern on that variant.
+        ///
+        /// See: <https://learn.microsoft.com/en-us/windows/win32/debug/getexceptioncode>
+        #[non_exhaustive]
+        #[derive(Debug, Clone, Copy)]
+        pub enum ExceptionCode {
+            $($name,)*
+            /// An exception code that does not match any of the known codes. It must not hold the\
+            /// value of a known code, as patterns on the variant of that code would not match it.
+            Other(u32),
+        }
+
+        impl ExceptionCode {
+            /// Converts a raw exception code into its known variant, falling back to `Other`.
+            ///
+            /// # Arguments
+            ///
+            /// * `raw` - The raw 32-bit value of the exception code.
+            pub const fn from_raw(raw: u32) -> Self {
+                match raw {
+                    $($value => ExceptionCode::$name,)*
+                    _ => ExceptionCode::Other(raw),
+                }
+            }
+
+            /// # Returns
+            ///
+            /// The raw 32-bit value of the exception code.
+            pub const fn raw(&self) -> u32 {
+                match self {
+                    $(ExceptionCode::$name => $value,)*
+                    ExceptionCode::Other(raw) => *raw,
+                }
+            }
+
+            /// # Returns
+            ///
+            /// The human-readable description of the exception code, if it is a known one.
+            const fn description(&self) -> Option<&'static str> {
+                // A known code held by `Other` is described like its variant.
+                match Self::from_raw(self.raw()) {
+                    $(ExceptionCode::$name => Some($description),)*
+                    ExceptionCode::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<u32> for ExceptionCode {
+            /// Converts a raw exception code into its known variant, falling back to `Other`.
+            fn from(raw: u32) -> Self {
+                Self::from_raw(raw)
+            }
+        }
+    };
+}
+
+exception_codes! {
+    Invalid = 0x0 => "invalid exception",
+    AccessViolation = 0xC0000005 => "the thread attempts to read from or write to a virtual address for which it does not have access",
+    ArrayBoundsExceeded = 0xC000008C => "the thread attempts to access an array element that is out of bounds and the underlying hardware supports bounds checking",
+    AssertionFailure = 0xC0000420 => "an assertion failure occurred",
+    BadFunctionTable = 0xC00000FF => "a malformed function table was encountered during an unwind operation",
+    BadStack = 0xC0000028 => "an invalid or unaligned stack was encountered during an unwind operation",
+    Breakpoint = 0x80000003 => "a breakpoint was encountered",
+    ClrException = 0xE0434352 => "a managed exception was thrown by the common language runtime",
+    ControlCExit = 0xC000013A => "the application terminated as a result of a CTRL+C",
+    CppException = 0xE06D7363 => "a C++ exception was thrown by the Microsoft Visual C++ runtime",
+    DataTypeMisalignment = 0x80000002 => "the thread attempts to read or write data that is misaligned on hardware that does not provide alignment",
+    DbgCommandException = 0x40010009 => "a command was sent to the debugger",
+    DbgControlBreak = 0x40010008 => "the debugger received a CTRL+BREAK",
+    DbgControlC = 0x40010005 => "the debugger received a CTRL+C",
+    DbgPrintException = 0x40010006 => "a debug string was sent to the debugger with OutputDebugStringA",
+    DbgPrintExceptionWide = 0x4001000A => "a debug string was sent to the debugger with OutputDebugStringW",
+    DbgRipException = 0x40010007 => "a RIP exception was sent to the debugger",
+    DelayLoadModNotFound = 0xC06D007E => "a module imported with delay loading could not be found",
+    DelayLoadProcNotFound = 0xC06D007F => "a procedure imported with delay loading could not be found",
+    DllInitFailed = 0xC0000142 => "the initialization routine of a dynamic link library failed",
+    DllNotFound = 0xC0000135 => "a required dynamic link library could not be found",
+    EnclaveViolation = 0xC00004A2 => "the thread attempted an operation that is not permitted inside of an enclave",
+    EntryPointNotFound = 0xC0000139 => "a procedure entry point could not be located in a dynamic link library",
+    FailFastException = 0xC0000602 => "a fail fast exception occurred, and exception handlers will not be invoked",
+    FatalUserCallbackException = 0xC000041D => "an unhandled exception was encountered during a user callback",
+    FltDenormalOperand = 0xC000008D => "one of the operands in a floating point operation is denormal",
+    FltDivideByZero = 0xC000008E => "the thread attempts to divide a floating point value by a floating point divisor of 0",
+    FltInexactResult = 0xC000008F => "the result of a floating point operation cannot be represented exactly as a decimal fraction",
+    FltInvalidOperation = 0xC0000090 => "this exception represents any floating point exception not included in this list",
+    FltMultipleFaults = 0xC00002B4 => "multiple floating point faults occurred in a single operation",
+    FltMultipleTraps = 0xC00002B5 => "multiple floating point traps occurred in a single operation",
+    FltOverflow = 0xC0000091 => "the exponent of a floating point operation is greater than the magnitude allowed by the corresponding type",
+    FltStackCheck = 0xC0000092 => "the stack has overflowed or underflowed, because of a floating point operation",
+    FltUnderflow = 0xC0000093 => "the exponent of a floating point operation is less than the magnitude allowed by the corresponding type",
+    GuardPage = 0x80000001 => "the thread accessed memory allocated with the PAGE_GUARD modifier",
+    HandleNotClosable = 0xC0000235 => "the thread attempted to close a handle that is protected from being closed",
+    HeapCorruption = 0xC0000374 => "a heap has been corrupted",
+    IllegalInstruction = 0xC000001D => "the thread tries to execute an invalid instruction",
+    InPageError = 0xC0000006 => "the thread tries to access a page that is not present, and the system is unable to load the page",
+    IntDivideByZero = 0xC0000094 => "the thread attempts to divide an integer value by an integer divisor of 0",
+    IntOverflow = 0xC0000095 => "the result of an integer operation creates a value that is too large to be held by the destination register",
+    InvalidCRuntimeParameter = 0xC0000417 => "an invalid parameter was passed to a C runtime function",
+    InvalidDisposition = 0xC0000026 => "an exception handler returns an invalid disposition to the exception dispatcher",
+    InvalidExceptionHandler = 0xC00001A5 => "an invalid exception handler routine has been detected",
+    InvalidHandle = 0xC0000008 => "the thread used a handle to a kernel object that was invalid",
+    InvalidParameter = 0xC000000D => "an invalid parameter was passed to a service or function",
+    InvalidUnwindTarget = 0xC0000029 => "an invalid unwind target was encountered during an unwind operation",
+    Longjump = 0x80000026 => "a long jump has been executed",
+    NoMemory = 0xC0000017 => "not enough virtual memory or paging file quota is available to complete the operation",
+    NonContinuableException = 0xC0000025 => "the thread attempts to continue execution after a non-continuable exception occurs",
+    OrdinalNotFound = 0xC0000138 => "an ordinal could not be located in a dynamic link library",
+    PossibleDeadlock = 0xC0000194 => "a possible deadlock condition was detected",
+    PrivilegedInstruction = 0xC0000096 => "the thread attempts to execute an instruction with an operation that is not allowed in the current computer mode",
+    RegNatConsumption = 0xC00002C9 => "a register NaT consumption fault occurred",
+    SetThreadName = 0x406D1388 => "the name of a thread was sent to the debugger",
+    SingleStep = 0x80000004 => "a trace trap or other single instruction mechanism signals that one instruction is executed",
+    StackBufferOverrun = 0xC0000409 => "the system detected an overrun of a stack-based buffer, or a fail fast request was made",
+    StackOverflow = 0xC00000FD => "the thread used up its stack",
+    Unwind = 0xC0000027 => "an unwind operation was initiated",
+    UnwindConsolidate = 0x80000029 => "a frame consolidation has been executed",
+    Wx86Breakpoint = 0x4000001F => "a breakpoint was encountered in an emulated x86 process",
+    Wx86SingleStep = 0x4000001E => "a single step trap was encountered in an emulated x86 process",
+}
+
+impl From<ExceptionCode> for u32 {
+    fn from(code: ExceptionCode) -> Self {
+        code.raw()
+    }
+}
+
+impl PartialEq for ExceptionCode {
+    /// Compares the raw values, so that `Other` never differs from the equivalent known code.
+    fn eq(&self, other: &Self) -> bool {
+        self.raw() == other.raw()
+    }
+}
+
+impl Eq for ExceptionCode {}
+
+impl core::hash::Hash for ExceptionCode {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.raw().hash(state);
+    }
 }
 
 impl core::fmt::Display for ExceptionCode {
@@ -44,31 +162,9 @@ impl core::fmt::Display for ExceptionCode {
     ///
     /// Whether the formatting operation succeeded.
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        match self {
-            ExceptionCode::Invalid => write!(f, "invalid exception"),
-            ExceptionCode::AccessViolation => write!(f, "the thread attempts to read from or write to a virtual address for which it does not have access"),
-            ExceptionCode::ArrayBoundsExceeded => write!(f, "the thread attempts to access an array element that is out of bounds and the underlying hardware supports bounds checking"),
-            ExceptionCode::Breakpoint => write!(f, "a breakpoint was encountered"),
-            ExceptionCode::DataTypeMisalignment => write!(f, "the thread attempts to read or write data that is misaligned on hardware that does not provide alignment"),
-            ExceptionCode::FltDenormalOperand => write!(f, "one of the operands in a floating point operation is denormal"),
-            ExceptionCode::FltDivideByZero => write!(f, "the thread attempts to divide a floating point value by a floating point divisor of 0"),
-            ExceptionCode::FltInexactResult => write!(f, "the result of a floating point operation cannot be represented exactly as a decimal fraction"),
-            ExceptionCode::FltInvalidOperation => write!(f, "this exception represents any floating point exception not included in this list"),
-            ExceptionCode::FltOverflow => write!(f, "the exponent of a floating point operation is greater than the magnitude allowed by the corresponding type"),
-            ExceptionCode::FltStackCheck => write!(f, "the stack has overflowed or underflowed, because of a floating point operation"),
-            ExceptionCode::FltUnderflow => write!(f, "the exponent of a floating point operation is less than the magnitude allowed by the corresponding type"),
-            ExceptionCode::GuardPage => write!(f, "the thread accessed memory allocated with the PAGE_GUARD modifier"),
-            ExceptionCode::IllegalInstruction => write!(f, "the thread tries to execute an invalid instruction"),
-            ExceptionCode::InPageError => write!(f, "the thread tries to access a page that is not present, and the system is unable to load the page"),
-            ExceptionCode::IntDivideByZero => write!(f, "the thread attempts to divide an integer value by an integer divisor of 0"),
-            ExceptionCode::IntOverflow => write!(f, "the result of an integer operation creates a value that is too large to be held by the destination register"),
-            ExceptionCode::InvalidDisposition => write!(f, "an exception handler returns an invalid disposition to the exception dispatcher"),
-            ExceptionCode::InvalidHandle => write!(f, "the thread used a handle to a kernel object that was invalid"),
-            ExceptionCode::NonContinuableException => write!(f, "the thread attempts to continue execution after a non-continuable exception occurs"),
-            ExceptionCode::PrivilegedInstruction => write!(f, "the thread attempts to execute an instruction with an operation that is not allowed in the current computer mode"),
-            ExceptionCode::SingleStep => write!(f, "a trace trap or other single instruction mechanism signals that one instruction is executed"),
-            ExceptionCode::StackOverflow => write!(f, "the thread used up its stack"),
-            ExceptionCode::UnwindConsolidate => write!(f, "a frame consolidation has been executed")
+        match self.description() {
+            Some(description) => write!(f, "{}", description),
+            None => write!(f, "unknown exception (0x{:08X})", self.raw()),
         }
     }
 }
@@ -0,0 +1,175 @@
+use crate::registers::Registers;
+
+/// Generates a setter for the register stored at the given index of the register list.
+macro_rules! set_reg {
+    ($name:ident, $reg:ident, $index:expr) => {
+        #[doc = concat!("Sets the value of the `", stringify!($reg), "` register.")]
+        #[inline]
+        pub fn $name(&mut self, value: usize) {
+            self.registers.list_mut()[$index] = value;
+        }
+    };
+}
+
+/// Mutable view of the registers captured along with an exception, which the execution
+/// continues with when the exception is resumed.
+///
+/// The changes only take effect if the handler returns `Disposition::ContinueExecution`.
+///
+/// The program counter is reported as the system does: on POSIX systems breakpoints leave it
+/// past the `int3` instruction on x86, while on Windows it points to the instruction itself.
+pub struct ContextMut<'a> {
+    registers: &'a mut Registers,
+}
+
+impl<'a> ContextMut<'a> {
+    /// Creates a new mutable view of the given registers.
+    pub(crate) fn new(registers: &'a mut Registers) -> Self {
+        Self { registers }
+    }
+
+    /// # Returns
+    ///
+    /// The registers the execution continues with, including the changes made so far.
+    pub fn registers(&self) -> &Registers {
+        self.registers
+    }
+
+    /// Moves the program counter forward, skipping the given number of bytes of code.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length of the instructions to skip, in bytes.
+    #[inline]
+    pub fn skip(&mut self, length: usize) {
+        let pc = self.registers.pc();
+        self.set_pc(pc.wrapping_add(length));
+    }
+}
+
+#[cfg(target_arch = "x86")]
+impl ContextMut<'_> {
+    set_reg!(set_eax, eax, 0);
+    set_reg!(set_ebx, ebx, 1);
+    set_reg!(set_ecx, ecx, 2);
+    set_reg!(set_edx, edx, 3);
+    set_reg!(set_esi, esi, 4);
+    set_reg!(set_edi, edi, 5);
+    set_reg!(set_ebp, ebp, 6);
+    set_reg!(set_esp, esp, 7);
+    set_reg!(set_eip, eip, 8);
+    set_reg!(set_eflags, eflags, 9);
+
+    /// Sets the value of the program counter, where the execution continues.
+    #[inline]
+    pub fn set_pc(&mut self, value: usize) {
+        self.registers.list_mut()[8] = value;
+    }
+
+    /// Sets the value of the stack pointer.
+    #[inline]
+    pub fn set_sp(&mut self, value: usize) {
+        self.registers.list_mut()[7] = value;
+    }
+
+    /// Sets the value of the frame pointer.
+    #[inline]
+    pub fn set_fp(&mut self, value: usize) {
+        self.registers.list_mut()[6] = value;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl ContextMut<'_> {
+    set_reg!(set_rax, rax, 0);
+    set_reg!(set_rbx, rbx, 1);
+    set_reg!(set_rcx, rcx, 2);
+    set_reg!(set_rdx, rdx, 3);
+    set_reg!(set_rsi, rsi, 4);
+    set_reg!(set_rdi, rdi, 5);
+    set_reg!(set_rbp, rbp, 6);
+    set_reg!(set_rsp, rsp, 7);
+    set_reg!(set_r8, r8, 8);
+    set_reg!(set_r9, r9, 9);
+    set_reg!(set_r10, r10, 10);
+    set_reg!(set_r11, r11, 11);
+    set_reg!(set_r12, r12, 12);
+    set_reg!(set_r13, r13, 13);
+    set_reg!(set_r14, r14, 14);
+    set_reg!(set_r15, r15, 15);
+    set_reg!(set_rip, rip, 16);
+    set_reg!(set_rflags, rflags, 17);
+
+    /// Sets the value of the program counter, where the execution continues.
+    #[inline]
+    pub fn set_pc(&mut self, value: usize) {
+        self.registers.list_mut()[16] = value;
+    }
+
+    /// Sets the value of the stack pointer.
+    #[inline]
+    pub fn set_sp(&mut self, value: usize) {
+        self.registers.list_mut()[7] = value;
+    }
+
+    /// Sets the value of the frame pointer.
+    #[inline]
+    pub fn set_fp(&mut self, value: usize) {
+        self.registers.list_mut()[6] = value;
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl ContextMut<'_> {
+    set_reg!(set_x0, x0, 0);
+    set_reg!(set_x1, x1, 1);
+    set_reg!(set_x2, x2, 2);
+    set_reg!(set_x3, x3, 3);
+    set_reg!(set_x4, x4, 4);
+    set_reg!(set_x5, x5, 5);
+    set_reg!(set_x6, x6, 6);
+    set_reg!(set_x7, x7, 7);
+    set_reg!(set_x8, x8, 8);
+    set_reg!(set_x9, x9, 9);
+    set_reg!(set_x10, x10, 10);
+    set_reg!(set_x11, x11, 11);
+    set_reg!(set_x12, x12, 12);
+    set_reg!(set_x13, x13, 13);
+    set_reg!(set_x14, x14, 14);
+    set_reg!(set_x15, x15, 15);
+    set_reg!(set_x16, x16, 16);
+    set_reg!(set_x17, x17, 17);
+    set_reg!(set_x18, x18, 18);
+    set_reg!(set_x19, x19, 19);
+    set_reg!(set_x20, x20, 20);
+    set_reg!(set_x21, x21, 21);
+    set_reg!(set_x22, x22, 22);
+    set_reg!(set_x23, x23, 23);
+    set_reg!(set_x24, x24, 24);
+    set_reg!(set_x25, x25, 25);
+    set_reg!(set_x26, x26, 26);
+    set_reg!(set_x27, x27, 27);
+    set_reg!(set_x28, x28, 28);
+    set_reg!(set_x29, x29, 29);
+    set_reg!(set_x30, x30, 30);
+    set_reg!(set_lr, lr, 30);
+    set_reg!(set_cpsr, cpsr, 33);
+
+    /// Sets the value of the program counter, where the execution continues.
+    #[inline]
+    pub fn set_pc(&mut self, value: usize) {
+        self.registers.list_mut()[32] = value;
+    }
+
+    /// Sets the value of the stack pointer.
+    #[inline]
+    pub fn set_sp(&mut self, value: usize) {
+        self.registers.list_mut()[31] = value;
+    }
+
+    /// Sets the value of the frame pointer.
+    #[inline]
+    pub fn set_fp(&mut self, value: usize) {
+        self.registers.list_mut()[29] = value;
+    }
+}
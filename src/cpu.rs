@@ -0,0 +1,317 @@
+//! Detection of the instructions supported by the processor, by executing them.
+//!
+//! An instruction that is not supported raises an `IllegalInstruction` exception, which works\
+//! even where the processor and the operating system disagree on what is available.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{try_seh_only, ExceptionCode};
+
+/// Executes the procedure, to find out whether the instructions it contains are supported.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure that executes the candidate instructions.
+///
+/// # Returns
+///
+/// Whether the procedure ran without raising an `IllegalInstruction` or\
+/// `PrivilegedInstruction` exception. Other exceptions keep propagating.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+/// # {
+/// use microseh::cpu;
+///
+/// assert!(cpu::probe(|| unsafe { core::arch::asm!("nop") }));
+/// assert!(!cpu::probe(|| unsafe { core::arch::asm!("ud2") }));
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+pub fn probe<F>(proc: F) -> bool
+where
+    F: FnMut(),
+{
+    try_seh_only(
+        &[
+            ExceptionCode::IllegalInstruction,
+            ExceptionCode::PrivilegedInstruction,
+        ],
+        proc,
+    )
+    .is_ok()
+}
+
+/// Result of a probe that has not run yet.
+const UNKNOWN: u8 = 0;
+/// Result of a probe whose instructions are not supported.
+const UNSUPPORTED: u8 = 1;
+/// Result of a probe whose instructions are supported.
+const SUPPORTED: u8 = 2;
+
+/// Named probe, whose result is computed the first time it is needed and then shared by\
+/// every thread of the process.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+/// # {
+/// use microseh::cpu::Feature;
+///
+/// static F16C: Feature = Feature::new("f16c", || unsafe {
+///     core::arch::asm!("vcvtph2ps xmm0, xmm0", out("xmm0") _);
+/// });
+///
+/// println!("{}: {}", F16C.name(), F16C.is_supported());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Feature {
+    name: &'static str,
+    probe: fn(),
+    state: AtomicU8,
+}
+
+impl Feature {
+    /// Creates a new feature, whose probe has not run yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the feature.
+    /// * `probe` - The procedure that executes the instructions of the feature.
+    pub const fn new(name: &'static str, probe: fn()) -> Self {
+        Self {
+            name,
+            probe,
+            state: AtomicU8::new(UNKNOWN),
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The name of the feature.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// # Returns
+    ///
+    /// Whether the instructions of the feature are supported, running the probe if this is\
+    /// the first time the feature is queried.
+    ///
+    /// # Panics
+    ///
+    /// If exception handling is disabled in the build, which occurs when the library is\
+    /// built for a platform that is neither Windows nor a POSIX system.
+    pub fn is_supported(&self) -> bool {
+        // Threads that race to query the feature may each run the probe, which always gives
+        // the same result, so there is nothing to synchronize.
+        match self.state.load(Ordering::Relaxed) {
+            SUPPORTED => true,
+            UNSUPPORTED => false,
+            _ => {
+                let supported = probe(self.probe);
+                let state = match supported {
+                    true => SUPPORTED,
+                    false => UNSUPPORTED,
+                };
+
+                self.state.store(state, Ordering::Relaxed);
+                supported
+            }
+        }
+    }
+}
+
+/// Creates a feature whose probe executes the given instructions, which may clobber any\
+/// register that is not preserved across calls.
+macro_rules! feature {
+    ($name:literal, $($instruction:literal),+) => {
+        Feature::new($name, || unsafe {
+            core::arch::asm!($($instruction),+, clobber_abi("C"), options(nomem, nostack));
+        })
+    };
+}
+
+/// Features of the x86 architecture, named as in `is_x86_feature_detected!`.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static FEATURES: [Feature; 13] = [
+    feature!("popcnt", "popcnt eax, eax"),
+    feature!("sse4.2", "crc32 eax, eax"),
+    feature!("aes", "aesenc xmm0, xmm0"),
+    feature!("pclmulqdq", "pclmulqdq xmm0, xmm0, 0"),
+    feature!("sha", "sha256rnds2 xmm0, xmm0"),
+    feature!("rdrand", "rdrand eax"),
+    feature!("rdseed", "rdseed eax"),
+    feature!("bmi1", "andn eax, eax, eax"),
+    feature!("bmi2", "pdep eax, eax, eax"),
+    feature!("avx", "vxorps ymm0, ymm0, ymm0"),
+    feature!("avx2", "vpxor ymm0, ymm0, ymm0"),
+    feature!("fma", "vfmadd231ps ymm0, ymm0, ymm0"),
+    feature!("avx512f", "vpxord zmm0, zmm0, zmm0"),
+];
+
+/// Features of the AArch64 architecture, named as in `is_aarch64_feature_detected!`. The\
+/// instructions are encoded by hand, as the assembler rejects those of disabled extensions.
+#[cfg(target_arch = "aarch64")]
+static FEATURES: [Feature; 7] = [
+    // crc32b w0, w0, w0
+    feature!("crc", ".inst 0x1ac04000"),
+    // aese v0.16b, v0.16b
+    feature!("aes", ".inst 0x4e284800"),
+    // sha256h q0, q0, v0.4s
+    feature!("sha2", ".inst 0x5e004000"),
+    // mrs x0, rndr
+    feature!("rand", ".inst 0xd53b2400"),
+    // sdot v0.4s, v0.16b, v0.16b
+    feature!("dotprod", ".inst 0x4e809400"),
+    // fadd v0.8h, v0.8h, v0.8h
+    feature!("fp16", ".inst 0x4e401400"),
+    // rdvl x0, #1
+    feature!("sve", ".inst 0x04bf5020"),
+];
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+static FEATURES: [Feature; 0] = [];
+
+/// # Returns
+///
+/// The features that can be probed on the current architecture.
+pub fn features() -> &'static [Feature] {
+    &FEATURES
+}
+
+/// # Arguments
+///
+/// * `name` - The name of one of the features of the current architecture.
+///
+/// # Returns
+///
+/// * `Some(bool)` - Whether the instructions of the feature are supported.
+/// * `None` - If the feature is not known on the current architecture.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::cpu;
+///
+/// if cpu::is_supported("avx512f") == Some(true) {
+///     println!("using the AVX-512 implementation");
+/// }
+///
+/// assert_eq!(cpu::is_supported("quantum"), None);
+/// ```
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+pub fn is_supported(name: &str) -> Option<bool> {
+    features()
+        .iter()
+        .find(|feature| feature.name == name)
+        .map(Feature::is_supported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::sync::atomic::AtomicUsize;
+
+    const INVALID_PTR: *mut i32 = core::mem::align_of::<i32>() as _;
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn probe_instructions() {
+        assert!(probe(|| unsafe { core::arch::asm!("nop") }));
+        assert!(!probe(|| unsafe { core::arch::asm!("ud2") }));
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn probe_instructions() {
+        assert!(probe(|| unsafe { core::arch::asm!("nop") }));
+        assert!(!probe(|| unsafe { core::arch::asm!("udf #0") }));
+    }
+
+    #[test]
+    fn probe_propagates_other_exceptions() {
+        let ex = crate::try_seh(|| {
+            probe(|| unsafe {
+                INVALID_PTR.read_volatile();
+            })
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    fn memoized() {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        static COUNTED: Feature = Feature::new("counted", || {
+            RUNS.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert!(COUNTED.is_supported());
+        assert!(COUNTED.is_supported());
+        assert_eq!(RUNS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn unknown_name() {
+        assert_eq!(is_supported("quantum"), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+    fn matches_std_detection() {
+        assert_eq!(
+            is_supported("popcnt"),
+            Some(std::is_x86_feature_detected!("popcnt"))
+        );
+        assert_eq!(
+            is_supported("sse4.2"),
+            Some(std::is_x86_feature_detected!("sse4.2"))
+        );
+        assert_eq!(
+            is_supported("rdrand"),
+            Some(std::is_x86_feature_detected!("rdrand"))
+        );
+        assert_eq!(
+            is_supported("avx2"),
+            Some(std::is_x86_feature_detected!("avx2"))
+        );
+        assert_eq!(
+            is_supported("avx512f"),
+            Some(std::is_x86_feature_detected!("avx512f"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn queried_from_many_threads() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    features()
+                        .iter()
+                        .map(Feature::is_supported)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let expected: Vec<bool> = features().iter().map(Feature::is_supported).collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    }
+}
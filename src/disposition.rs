@@ -0,0 +1,15 @@
+/// Represents the action to take once an exception has been inspected by a filter, mirroring the
+/// values that the filter expression of an `__except` block can evaluate to.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Disposition {
+    /// The exception is handled, and is returned as an error from the guarded procedure.
+    ExecuteHandler = 1,
+    /// The exception is not handled, and is passed on to the outer handlers. If no handler
+    /// accepts it, the default behavior of the system applies, which usually terminates the
+    /// process.
+    ContinueSearch = 0,
+    /// Execution resumes at the point where the exception occurred. Unless the cause of the
+    /// exception was removed, it is raised again.
+    ContinueExecution = -1,
+}
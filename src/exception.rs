@@ -1,21 +1,233 @@
-use crate::{code::ExceptionCode};
+use core::ptr::NonNull;
 
-/// Represents an exception that occurs during program execution, along with additional
-/// context information.
+use crate::{
+    code::ExceptionCode,
+    flags::ExceptionFlags,
+    kind::ExceptionKind,
+    snapshot::{
+        RawSnapshot, Snapshot, CODE_SNAPSHOT_SIZE, DATA_SNAPSHOT_SIZE, STACK_SNAPSHOT_SIZE,
+    },
+};
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+use crate::{fpu::FpuState, registers::Registers};
+
+/// Maximum number of parameters that can be attached to an exception.
+const MAXIMUM_PARAMETERS: usize = 15;
+
+/// Maximum number of nested exception records that are captured along with an exception.
+const MAXIMUM_NESTED: usize = 4;
+
+/// Mirrors the `RECORD` structure of the C stub, which holds the data of an `EXCEPTION_RECORD`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Record {
+    code: u32,
+    flags: u32,
+    number_parameters: u32,
+    address: usize,
+    information: [usize; MAXIMUM_PARAMETERS],
+}
+
+impl Record {
+    /// Creates a new record with default values.
+    const fn empty() -> Self {
+        Self {
+            code: ExceptionCode::Invalid.raw(),
+            flags: 0,
+            number_parameters: 0,
+            address: 0,
+            information: [0; MAXIMUM_PARAMETERS],
+        }
+    }
+
+    /// Creates a new record for a software exception, raised at the address of its caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code of the exception.
+    /// * `flags` - The flags of the exception.
+    /// * `parameters` - The parameters of the exception, truncated to the maximum number
+    ///   of parameters.
+    pub(crate) fn new(code: ExceptionCode, flags: ExceptionFlags, parameters: &[usize]) -> Self {
+        let count = parameters.len().min(MAXIMUM_PARAMETERS);
+        let mut record = Self {
+            code: code.raw(),
+            flags: flags.raw(),
+            number_parameters: count as u32,
+            ..Self::empty()
+        };
+
+        record.information[..count].copy_from_slice(&parameters[..count]);
+        record
+    }
+}
+
+/// Mirrors the `EXCEPTION` structure of the C stub, which is filled when an exception occurs.
 #[repr(C)]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Exception {
-    code: ExceptionCode
+pub(crate) struct RawException {
+    record: Record,
+    signal_info: SignalInfo,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    registers: Registers,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    fpu: FpuState,
+    nested_count: u32,
+    nested: [Record; MAXIMUM_NESTED],
+    code: RawSnapshot<CODE_SNAPSHOT_SIZE>,
+    stack: RawSnapshot<STACK_SNAPSHOT_SIZE>,
+    data: RawSnapshot<DATA_SNAPSHOT_SIZE>,
 }
 
-impl Exception {
-    /// Creates a new exception with default values.
+impl RawException {
+    /// Creates a new raw exception with default values.
     ///
     /// Exceptions created with this function are to be considered invalid, and should
     /// only be used as a placeholder.
     pub(crate) fn empty() -> Self {
         Self {
-            code: ExceptionCode::Invalid,
+            record: Record::empty(),
+            signal_info: SignalInfo::empty(),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+            registers: Registers::empty(),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+            fpu: FpuState::empty(),
+            nested_count: 0,
+            nested: [Record::empty(); MAXIMUM_NESTED],
+            code: RawSnapshot::empty(),
+            stack: RawSnapshot::empty(),
+            data: RawSnapshot::empty(),
+        }
+    }
+
+    /// Creates a new raw exception that only holds the data of a nested record.
+    fn from_record(record: Record) -> Self {
+        Self {
+            record,
+            ..Self::empty()
+        }
+    }
+}
+
+/// Raw data owned by a caught exception. It is boxed when the `std` feature is enabled, so that
+/// the results holding exceptions stay small.
+#[cfg(feature = "std")]
+type OwnedRaw = Box<RawException>;
+#[cfg(not(feature = "std"))]
+type OwnedRaw = RawException;
+
+/// # Returns
+///
+/// The raw data, owned the way caught exceptions store it.
+#[inline(always)]
+fn own(raw: RawException) -> OwnedRaw {
+    #[cfg(feature = "std")]
+    return Box::new(raw);
+    #[cfg(not(feature = "std"))]
+    raw
+}
+
+/// Location of the raw data of an exception.
+#[cfg_attr(not(feature = "std"), allow(clippy::large_enum_variant))]
+enum Storage {
+    /// The exception was caught, and owns its data.
+    Owned(OwnedRaw),
+    /// The exception is being dispatched to a filter, and its data is the one filled by the C
+    /// stub in the frame of the guarded region.
+    Dispatched(NonNull<RawException>),
+}
+
+// SAFETY: Dispatched exceptions are only handed out by reference to the filters, which run on
+//         the thread that raised them, while the data they point to is alive.
+unsafe impl Send for Storage {}
+unsafe impl Sync for Storage {}
+
+/// Represents an exception that occurs during program execution, along with additional
+/// context information.
+pub struct Exception {
+    raw: Storage,
+    #[cfg(feature = "std")]
+    cause: Option<Box<Exception>>,
+}
+
+impl Exception {
+    /// Creates a new exception that owns the data filled by the C stub, once the exception has
+    /// been caught and is no longer being dispatched.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The data of the exception.
+    pub(crate) fn caught(raw: RawException) -> Self {
+        let mut exception = Self {
+            raw: Storage::Owned(own(raw)),
+            #[cfg(feature = "std")]
+            cause: None,
+        };
+
+        exception.link_nested();
+        exception
+    }
+
+    /// Creates a new exception that refers to the data filled by the C stub, to be handed to
+    /// the filters while the exception is dispatched, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - The data of the exception, which must outlive the returned exception.
+    pub(crate) unsafe fn dispatched(raw: *mut RawException) -> Self {
+        Self {
+            raw: Storage::Dispatched(NonNull::new_unchecked(raw)),
+            #[cfg(feature = "std")]
+            cause: None,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The raw exception data.
+    fn raw(&self) -> &RawException {
+        match &self.raw {
+            Storage::Owned(raw) => raw,
+            // SAFETY: The data outlives the exception, as required by `dispatched`.
+            Storage::Dispatched(raw) => unsafe { raw.as_ref() },
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The raw exception data, which the C stub reads back when the execution is continued.
+    fn raw_mut(&mut self) -> &mut RawException {
+        match &mut self.raw {
+            Storage::Owned(raw) => raw,
+            // SAFETY: The data outlives the exception, as required by `dispatched`.
+            Storage::Dispatched(raw) => unsafe { raw.as_mut() },
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Whether the exception is the one created by `dispatched` with the provided data.
+    pub(crate) fn is_dispatched_from(&self, raw: *const RawException) -> bool {
+        matches!(self.raw, Storage::Dispatched(dispatched) if core::ptr::eq(dispatched.as_ptr(), raw))
+    }
+
+    /// Builds the chain of causes from the nested records filled by the C stub.
+    fn link_nested(&mut self) {
+        // The first nested record is the direct cause of the exception, so the chain is built
+        // starting from the outermost record.
+        #[cfg(feature = "std")]
+        {
+            let raw = self.raw();
+            let count = (raw.nested_count as usize).min(MAXIMUM_NESTED);
+            self.cause = raw.nested[..count]
+                .iter()
+                .rev()
+                .fold(None, |cause, record| {
+                    Some(Box::new(Exception {
+                        raw: Storage::Owned(own(RawException::from_record(*record))),
+                        cause,
+                    }))
+                });
         }
     }
 
@@ -23,8 +235,284 @@ impl Exception {
     ///
     /// The system-specific code of the exception.
     pub fn code(&self) -> ExceptionCode {
+        ExceptionCode::from(self.raw().record.code)
+    }
+
+    /// # Returns
+    ///
+    /// The flags of the exception.
+    pub fn flags(&self) -> ExceptionFlags {
+        ExceptionFlags::from_raw(self.raw().record.flags)
+    }
+
+    /// # Returns
+    ///
+    /// The raw parameters attached to the exception, as found in the `ExceptionInformation`
+    /// field of the `EXCEPTION_RECORD` structure.
+    ///
+    /// On POSIX platforms, memory faults are given the same parameters as on Windows.
+    pub fn parameters(&self) -> &[usize] {
+        let count = (self.raw().record.number_parameters as usize).min(MAXIMUM_PARAMETERS);
+        &self.raw().record.information[..count]
+    }
+
+    /// # Returns
+    ///
+    /// The information about the signal that was translated into this exception.
+    #[cfg(unix)]
+    pub fn signal_info(&self) -> &SignalInfo {
+        &self.raw().signal_info
+    }
+
+    /// # Returns
+    ///
+    /// The address of the instruction that caused the exception.
+    pub fn address(&self) -> usize {
+        self.raw().record.address
+    }
+
+    /// # Returns
+    ///
+    /// The address of the data that could not be accessed, if the exception is a memory fault
+    /// such as an access violation or an in-page error.
+    pub fn data_address(&self) -> Option<usize> {
+        self.kind().data_address()
+    }
+
+    /// # Returns
+    ///
+    /// The kind of the exception, along with the data that is specific to its code.
+    pub fn kind(&self) -> ExceptionKind {
+        ExceptionKind::decode(self.code(), self.parameters())
+    }
+
+    /// # Returns
+    ///
+    /// The state of the general purpose registers at the point where the exception occurred.
+    ///
+    /// The registers of nested exceptions returned by `cause` are not captured, and are all
+    /// set to zero. On Windows, the registers are only captured with the `std` feature, and are
+    /// also all set to zero otherwise.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn registers(&self) -> &Registers {
+        &self.raw().registers
+    }
+
+    /// Replaces the captured registers, which the C stub applies back when the execution
+    /// is continued.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(crate) fn set_registers(&mut self, registers: Registers) {
+        self.raw_mut().registers = registers;
+    }
+
+    /// # Returns
+    ///
+    /// The state of the floating-point and vector registers at the point where the exception
+    /// occurred, or `None` if it was not captured.
+    ///
+    /// This is the state that caused floating-point exceptions such as `FltDivideByZero`,
+    /// whose flags are found in the status registers.
+    ///
+    /// On Windows, the state is only captured with the `std` feature, as kernel drivers cannot
+    /// locate the extended state of the context.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn fpu(&self) -> Option<&FpuState> {
+        Some(&self.raw().fpu).filter(|fpu| fpu.is_captured())
+    }
+
+    /// # Returns
+    ///
+    /// The bytes at the address of the instruction that caused the exception, if they could
+    /// be read.
+    ///
+    /// Snapshots are only captured by `try_seh_with_snapshots`, and are `None` otherwise.
+    pub fn code_snapshot(&self) -> Option<Snapshot<'_>> {
+        self.raw().code.get()
+    }
+
+    /// # Returns
+    ///
+    /// The bytes on top of the stack at the point where the exception occurred, if they could
+    /// be read, and were captured by `try_seh_with_snapshots`.
+    pub fn stack_snapshot(&self) -> Option<Snapshot<'_>> {
+        self.raw().stack.get()
+    }
+
+    /// # Returns
+    ///
+    /// The bytes around the address of the data that could not be accessed, if the exception
+    /// is a memory fault and any of them could be read, and were captured by
+    /// `try_seh_with_snapshots`.
+    ///
+    /// The window is centered on the data address, but the bytes past it usually belong to
+    /// the same inaccessible page, and are not part of the snapshot.
+    pub fn data_snapshot(&self) -> Option<Snapshot<'_>> {
+        self.raw().data.get()
+    }
+
+    /// # Returns
+    ///
+    /// The exception that was being dispatched when this exception was raised, if any.
+    ///
+    /// Only the record of nested exceptions is available, and the chain is limited to the
+    /// first few records. Nested exceptions are never reported on POSIX platforms, nor to the
+    /// filters, which run before the chain is built.
+    #[cfg(feature = "std")]
+    pub fn cause(&self) -> Option<&Exception> {
+        self.cause.as_deref()
+    }
+
+    /// Raises the exception again with the same code, flags, address and parameters, so that the
+    /// outer handlers see it as if it had never been caught.
+    ///
+    /// On POSIX platforms, the signal the exception was translated from is sent again, so the
+    /// default action of the system is the one of the original fault.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use microseh::{try_seh, ExceptionCode};
+    ///
+    /// let ex = try_seh(|| {
+    ///     if let Err(ex) = try_seh(|| unsafe {
+    ///         core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+    ///     }) {
+    ///         // Not ours to handle.
+    ///         unsafe { ex.rethrow() }
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    /// ```
+    ///
+    /// # Caveats
+    ///
+    /// On Windows, the `std` feature is required, as the exception is raised through ntdll,
+    /// which kernel drivers cannot link against.
+    ///
+    /// # Safety
+    ///
+    /// Like any other exception, the rethrown exception leaves the frames between the caller
+    /// and the handler that catches it without running any destructor. If no handler catches
+    /// it, the process is terminated.
+    ///
+    /// If a filter continues the execution, a `NonContinuableException` is raised instead, as
+    /// the context the exception occurred in no longer exists.
+    ///
+    /// # Panics
+    ///
+    /// If exception handling is disabled in the build, which occurs when the library is
+    /// built for a platform that is neither Windows nor a POSIX system.
+    #[cfg(any(not(windows), feature = "std"))]
+    pub unsafe fn rethrow(self) -> ! {
+        // The exception is never returned to, so its data is moved to the stack and the rest
+        // is released first.
+        #[cfg(all(any(windows, unix), not(docsrs)))]
+        {
+            let raw = self.raw().clone();
+            #[cfg(feature = "std")]
+            drop(self);
+            crate::rethrow_exception(&raw);
+        }
+
+        #[cfg(any(not(any(windows, unix)), docsrs))]
+        panic!("exception handling is not available in this build of microseh")
+    }
+}
+
+impl Clone for Exception {
+    fn clone(&self) -> Self {
+        Self {
+            raw: Storage::Owned(own(self.raw().clone())),
+            #[cfg(feature = "std")]
+            cause: self.cause.clone(),
+        }
+    }
+}
+
+impl PartialEq for Exception {
+    fn eq(&self, other: &Self) -> bool {
+        // The chain of causes is built from the nested records, which are part of the data.
+        self.raw() == other.raw()
+    }
+}
+
+impl Eq for Exception {}
+
+impl core::hash::Hash for Exception {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.raw().hash(state);
+    }
+}
+
+impl core::fmt::Debug for Exception {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("Exception");
+        debug
+            .field("code", &self.code())
+            .field("address", &format_args!("{:#x}", self.address()))
+            .field("flags", &self.flags())
+            .field("parameters", &self.parameters());
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+        debug.field("registers", self.registers());
+
+        #[cfg(feature = "std")]
+        debug.field("cause", &self.cause);
+
+        debug.finish()
+    }
+}
+
+/// Represents the fields of the `siginfo_t` structure that describe the signal an exception
+/// was translated from, on POSIX platforms.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignalInfo {
+    signal: i32,
+    code: i32,
+    errno: i32,
+    address: usize,
+}
+
+impl SignalInfo {
+    /// Creates a new signal information with all the fields set to zero.
+    const fn empty() -> Self {
+        Self {
+            signal: 0,
+            code: 0,
+            errno: 0,
+            address: 0,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The number of the signal (`si_signo`).
+    pub fn signal(&self) -> i32 {
+        self.signal
+    }
+
+    /// # Returns
+    ///
+    /// The signal-specific code describing why the signal was sent (`si_code`).
+    pub fn code(&self) -> i32 {
         self.code
     }
+
+    /// # Returns
+    ///
+    /// The error number associated with the signal, which is usually zero (`si_errno`).
+    pub fn errno(&self) -> i32 {
+        self.errno
+    }
+
+    /// # Returns
+    ///
+    /// The faulting address of the signal (`si_addr`).
+    pub fn address(&self) -> usize {
+        self.address
+    }
 }
 
 impl core::fmt::Display for Exception {
@@ -38,11 +526,51 @@ impl core::fmt::Display for Exception {
     ///
     /// Whether the formatting operation succeeded.
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", self.code)
+        write!(f, "{}", self.code())
     }
 }
 
 /// In case the `std` feature is enabled, this implementation allows the exception to be
-/// treated as a standard error.
+/// treated as a standard error, whose source is the nested exception that caused it.
 #[cfg(feature = "std")]
-impl std::error::Error for Exception {}
+impl std::error::Error for Exception {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_chain() {
+        let mut raw = RawException::empty();
+        raw.record.code = ExceptionCode::AccessViolation.raw();
+        raw.nested_count = 2;
+        raw.nested[0].code = ExceptionCode::StackOverflow.raw();
+        raw.nested[1].code = ExceptionCode::Breakpoint.raw();
+        let ex = Exception::caught(raw);
+        let cause = ex.cause().unwrap();
+        assert_eq!(cause.code(), ExceptionCode::StackOverflow);
+        assert_eq!(cause.cause().unwrap().code(), ExceptionCode::Breakpoint);
+        assert!(cause.cause().unwrap().cause().is_none());
+
+        let source = std::error::Error::source(&ex).unwrap();
+        assert_eq!(source.to_string(), cause.to_string());
+    }
+
+    #[test]
+    fn boxed_when_caught() {
+        assert!(core::mem::size_of::<Exception>() <= 4 * core::mem::size_of::<usize>());
+
+        let mut raw = RawException::empty();
+        raw.record.code = ExceptionCode::Breakpoint.raw();
+        let dispatched = unsafe { Exception::dispatched(&mut raw) };
+        let caught = dispatched.clone();
+        assert_eq!(caught, dispatched);
+        assert!(matches!(caught.raw, Storage::Owned(_)));
+    }
+}
@@ -0,0 +1,77 @@
+/// Represents the flags of an exception, as stored in the `ExceptionFlags` field of the
+/// `EXCEPTION_RECORD` structure.
+///
+/// Hardware exceptions usually have no flags set. On POSIX platforms, the flags are only set
+/// for exceptions raised in software.
+///
+/// See: <https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-exception_record>
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ExceptionFlags(u32);
+
+impl ExceptionFlags {
+    /// Execution cannot continue after the exception.
+    pub const NONCONTINUABLE: Self = Self(0x1);
+    /// The exception is being dispatched as part of an unwind operation.
+    pub const UNWINDING: Self = Self(0x2);
+    /// The exception is being dispatched as part of an exit unwind operation.
+    pub const EXIT_UNWIND: Self = Self(0x4);
+    /// The stack was found to be invalid during dispatching.
+    pub const STACK_INVALID: Self = Self(0x8);
+    /// The exception was raised while another exception was being dispatched.
+    pub const NESTED_CALL: Self = Self(0x10);
+    /// The exception is being dispatched to the target frame of an unwind operation.
+    pub const TARGET_UNWIND: Self = Self(0x20);
+    /// The unwind operation collided with another unwind operation.
+    pub const COLLIDED_UNWIND: Self = Self(0x40);
+    /// The exception was raised in software rather than by the processor.
+    pub const SOFTWARE_ORIGINATE: Self = Self(0x80);
+
+    /// Creates a new set of flags from their raw value.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    /// # Returns
+    ///
+    /// The raw value of the flags.
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    /// # Returns
+    ///
+    /// Whether no flags are set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// # Returns
+    ///
+    /// Whether all the flags in `other` are also set in this set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for ExceptionFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for ExceptionFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl core::ops::BitAnd for ExceptionFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
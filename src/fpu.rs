@@ -0,0 +1,263 @@
+/// The upper halves of the YMM registers were captured.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const FEATURE_AVX: u32 = 0x4;
+/// The opmask registers and the upper halves of the ZMM registers were captured.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const FEATURE_AVX512: u32 = 0x8;
+
+/// Number of vector registers that are available without AVX-512.
+#[cfg(target_arch = "x86")]
+const NUM_VECTOR_REGISTERS: usize = 8;
+#[cfg(target_arch = "x86_64")]
+const NUM_VECTOR_REGISTERS: usize = 16;
+
+/// Snapshot of the floating-point and vector registers of the thread, taken at the point where
+/// an exception occurred.
+///
+/// The vector registers are only reported up to the width that was captured by the system,
+/// which depends on the extensions supported by the processor.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FpuState {
+    features: u32,
+    control_word: u16,
+    status_word: u16,
+    tag_word: u16,
+    reserved: u16,
+    mxcsr: u32,
+    st: [[u64; 2]; 8],
+    zmm: [[u64; 8]; 32],
+    opmask: [u64; 8],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl FpuState {
+    /// Creates a new state that holds no captured registers.
+    pub(crate) const fn empty() -> Self {
+        Self {
+            features: 0,
+            control_word: 0,
+            status_word: 0,
+            tag_word: 0,
+            reserved: 0,
+            mxcsr: 0,
+            st: [[0; 2]; 8],
+            zmm: [[0; 8]; 32],
+            opmask: [0; 8],
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Whether any register was captured.
+    pub(crate) fn is_captured(&self) -> bool {
+        self.features != 0
+    }
+
+    /// # Returns
+    ///
+    /// Whether the upper halves of the YMM registers were captured.
+    pub fn has_avx(&self) -> bool {
+        self.features & FEATURE_AVX != 0
+    }
+
+    /// # Returns
+    ///
+    /// Whether the ZMM and opmask registers were captured.
+    pub fn has_avx512(&self) -> bool {
+        self.features & FEATURE_AVX512 != 0
+    }
+
+    /// # Returns
+    ///
+    /// The x87 control word.
+    pub fn control_word(&self) -> u16 {
+        self.control_word
+    }
+
+    /// # Returns
+    ///
+    /// The x87 status word, which holds the exception flags of the x87 unit.
+    pub fn status_word(&self) -> u16 {
+        self.status_word
+    }
+
+    /// # Returns
+    ///
+    /// The x87 tag word, in the abridged 8-bit format used by `FXSAVE`.
+    pub fn tag_word(&self) -> u16 {
+        self.tag_word
+    }
+
+    /// # Returns
+    ///
+    /// The SSE control and status register, which holds the masks and the exception flags
+    /// of the SSE unit.
+    pub fn mxcsr(&self) -> u32 {
+        self.mxcsr
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the x87 register, relative to the top of the stack.
+    ///
+    /// # Returns
+    ///
+    /// * `Some([u8; 10])` - The 80-bit value of the register, in little-endian order.
+    /// * `None` - If the index is out of range.
+    pub fn st(&self, index: usize) -> Option<[u8; 10]> {
+        let st = self.st.get(index)?;
+        let mut bytes = [0; 10];
+        bytes[..8].copy_from_slice(&st[0].to_le_bytes());
+        bytes[8..].copy_from_slice(&st[1].to_le_bytes()[..2]);
+        Some(bytes)
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the XMM register.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u128)` - The value of the register.
+    /// * `None` - If the index is out of range, or if the register was not captured.
+    pub fn xmm(&self, index: usize) -> Option<u128> {
+        let zmm = self.vector(index)?;
+        Some(join(zmm[0], zmm[1]))
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the YMM register.
+    ///
+    /// # Returns
+    ///
+    /// * `Some([u128; 2])` - The value of the register, from its lower to its upper half.
+    /// * `None` - If the index is out of range, or if the register was not captured.
+    pub fn ymm(&self, index: usize) -> Option<[u128; 2]> {
+        if !self.has_avx() {
+            return None;
+        }
+
+        let zmm = self.vector(index)?;
+        Some([join(zmm[0], zmm[1]), join(zmm[2], zmm[3])])
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the ZMM register.
+    ///
+    /// # Returns
+    ///
+    /// * `Some([u128; 4])` - The value of the register, from its lowest to its highest part.
+    /// * `None` - If the index is out of range, or if the register was not captured.
+    pub fn zmm(&self, index: usize) -> Option<[u128; 4]> {
+        if !self.has_avx512() {
+            return None;
+        }
+
+        let zmm = self.vector(index)?;
+        Some([
+            join(zmm[0], zmm[1]),
+            join(zmm[2], zmm[3]),
+            join(zmm[4], zmm[5]),
+            join(zmm[6], zmm[7]),
+        ])
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the opmask register.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u64)` - The value of the register.
+    /// * `None` - If the index is out of range, or if the register was not captured.
+    pub fn opmask(&self, index: usize) -> Option<u64> {
+        if !self.has_avx512() {
+            return None;
+        }
+
+        self.opmask.get(index).copied()
+    }
+
+    /// # Returns
+    ///
+    /// The full width of the vector register at the given index, if it exists.
+    fn vector(&self, index: usize) -> Option<&[u64; 8]> {
+        // The upper 16 registers are only available in 64-bit mode with AVX-512.
+        let count = if self.has_avx512() && cfg!(target_arch = "x86_64") {
+            self.zmm.len()
+        } else {
+            NUM_VECTOR_REGISTERS
+        };
+
+        self.zmm[..count].get(index)
+    }
+}
+
+/// Combines the two 64-bit halves of a 128-bit value.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn join(low: u64, high: u64) -> u128 {
+    ((high as u128) << 64) | low as u128
+}
+
+/// Snapshot of the floating-point and vector registers of the thread, taken at the point where
+/// an exception occurred.
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FpuState {
+    features: u32,
+    fpcr: u32,
+    fpsr: u32,
+    v: [[u64; 2]; 32],
+}
+
+#[cfg(target_arch = "aarch64")]
+impl FpuState {
+    /// Creates a new state that holds no captured registers.
+    pub(crate) const fn empty() -> Self {
+        Self {
+            features: 0,
+            fpcr: 0,
+            fpsr: 0,
+            v: [[0; 2]; 32],
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Whether any register was captured.
+    pub(crate) fn is_captured(&self) -> bool {
+        self.features != 0
+    }
+
+    /// # Returns
+    ///
+    /// The floating-point control register, which holds the trap enable bits.
+    pub fn fpcr(&self) -> u32 {
+        self.fpcr
+    }
+
+    /// # Returns
+    ///
+    /// The floating-point status register, which holds the cumulative exception flags.
+    pub fn fpsr(&self) -> u32 {
+        self.fpsr
+    }
+
+    /// # Arguments
+    ///
+    /// * `index` - The index of the vector register.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(u128)` - The value of the register.
+    /// * `None` - If the index is out of range.
+    pub fn v(&self, index: usize) -> Option<u128> {
+        let v = self.v.get(index)?;
+        Some(((v[1] as u128) << 64) | v[0] as u128)
+    }
+}
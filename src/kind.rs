@@ -0,0 +1,121 @@
+use crate::{code::ExceptionCode, status::NtStatus};
+
+/// Magic number stored in the first parameter of exceptions thrown by the MSVC C++ runtime.
+const CPP_EXCEPTION_MAGIC: usize = 0x1993_0520;
+
+/// Represents the kind of memory access that caused a memory fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessKind {
+    /// The thread attempted to read the data.
+    Read,
+    /// The thread attempted to write the data.
+    Write,
+    /// The thread attempted to execute the data, violating data execution prevention (DEP).
+    Execute,
+    /// The kind of access reported by the system is not known to this library.
+    Other(usize),
+}
+
+impl From<usize> for AccessKind {
+    fn from(raw: usize) -> Self {
+        match raw {
+            0 => AccessKind::Read,
+            1 => AccessKind::Write,
+            8 => AccessKind::Execute,
+            _ => AccessKind::Other(raw),
+        }
+    }
+}
+
+/// Represents an exception along with the data that is specific to its code.
+///
+/// This is decoded from the raw parameters of the exception, and only covers the exceptions
+/// whose parameters have a documented meaning. Every other exception is reported as `Other`.
+///
+/// On POSIX platforms, memory faults report the address from `si_addr`, and the kind of access
+/// is taken from the fault information of the processor when available, falling back to
+/// `AccessKind::Read` otherwise.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExceptionKind {
+    /// The thread attempted an access to a virtual address it does not have access to.
+    AccessViolation { access: AccessKind, address: usize },
+    /// The thread attempted an access to a page that the system was unable to load.
+    ///
+    /// The status is the result of the failed I/O operation, which is not reported on
+    /// POSIX platforms.
+    InPageError {
+        access: AccessKind,
+        address: usize,
+        status: Option<NtStatus>,
+    },
+    /// The thread accessed memory allocated with the `PAGE_GUARD` modifier.
+    GuardPage { access: AccessKind, address: usize },
+    /// A stack-based buffer overrun was detected, or a fail fast request was made with the
+    /// given `FAST_FAIL_*` code.
+    StackBufferOverrun { fast_fail_code: Option<usize> },
+    /// A C++ exception was thrown by the MSVC runtime.
+    ///
+    /// The image base is only reported on 64-bit platforms, where the throw information is
+    /// stored as an offset relative to it.
+    CppException {
+        object: usize,
+        throw_info: usize,
+        image_base: Option<usize>,
+    },
+    /// Any other exception, which carries no documented parameters.
+    Other(ExceptionCode),
+}
+
+impl ExceptionKind {
+    /// Decodes the kind of an exception from its code and raw parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The code of the exception.
+    /// * `params` - The parameters attached to the exception.
+    pub(crate) fn decode(code: ExceptionCode, params: &[usize]) -> Self {
+        match (code, params) {
+            (ExceptionCode::AccessViolation, [access, address, ..]) => {
+                ExceptionKind::AccessViolation {
+                    access: AccessKind::from(*access),
+                    address: *address,
+                }
+            }
+            (ExceptionCode::InPageError, [access, address, rest @ ..]) => {
+                ExceptionKind::InPageError {
+                    access: AccessKind::from(*access),
+                    address: *address,
+                    status: rest.first().map(|status| NtStatus::new(*status as u32)),
+                }
+            }
+            (ExceptionCode::GuardPage, [access, address, ..]) => ExceptionKind::GuardPage {
+                access: AccessKind::from(*access),
+                address: *address,
+            },
+            (ExceptionCode::StackBufferOverrun, params) => ExceptionKind::StackBufferOverrun {
+                fast_fail_code: params.first().copied(),
+            },
+            (ExceptionCode::CppException, [CPP_EXCEPTION_MAGIC, object, throw_info, rest @ ..]) => {
+                ExceptionKind::CppException {
+                    object: *object,
+                    throw_info: *throw_info,
+                    image_base: rest.first().copied(),
+                }
+            }
+            _ => ExceptionKind::Other(code),
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The address of the data that could not be accessed, if this is a memory fault.
+    pub fn data_address(&self) -> Option<usize> {
+        match self {
+            ExceptionKind::AccessViolation { address, .. }
+            | ExceptionKind::InPageError { address, .. }
+            | ExceptionKind::GuardPage { address, .. } => Some(*address),
+            _ => None,
+        }
+    }
+}
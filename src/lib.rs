@@ -1,15 +1,67 @@
 #![allow(dead_code)]
+// Without `std`, exceptions cannot be boxed and carry the captured context by value.
+#![cfg_attr(not(feature = "std"), allow(clippy::result_large_err))]
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use core::{ffi::c_void, mem::MaybeUninit};
 
+use exception::RawException;
+#[cfg(all(any(windows, unix), not(docsrs), any(not(windows), feature = "std")))]
+use exception::Record;
+
+#[cfg(feature = "std")]
+mod arena;
 mod code;
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"),
+    any(not(windows), feature = "std")
+))]
+mod context;
+pub mod cpu;
+mod disposition;
 mod exception;
+mod flags;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+mod fpu;
+mod kind;
+#[cfg(feature = "std")]
+mod outcome;
+pub mod probe;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+mod registers;
+mod snapshot;
+#[cfg(any(all(windows, feature = "std"), all(unix, target_env = "gnu")))]
+mod stack;
+mod status;
 
+#[cfg(feature = "std")]
+pub use arena::Arena;
 pub use code::ExceptionCode;
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"),
+    any(not(windows), feature = "std")
+))]
+pub use context::ContextMut;
+pub use disposition::Disposition;
 pub use exception::Exception;
+#[cfg(unix)]
+pub use exception::SignalInfo;
+pub use flags::ExceptionFlags;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+pub use fpu::FpuState;
+pub use kind::{AccessKind, ExceptionKind};
+#[cfg(feature = "std")]
+pub use outcome::Outcome;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+pub use registers::Registers;
+pub use snapshot::Snapshot;
+#[cfg(any(all(windows, feature = "std"), all(unix, target_env = "gnu")))]
+pub use stack::StackRun;
+pub use status::{NtStatus, Severity};
 
 const MS_SUCCEEDED: u32 = 0x0;
+const MS_STACK_UNAVAILABLE: u32 = 0x2;
+const MS_CAPTURE_MEMORY: u32 = 0x1;
 
 /// Type alias for a function that converts a pointer to a function and executes it.
 type ProcExecutor = unsafe extern "system" fn(*mut c_void);
@@ -32,7 +84,49 @@ where
     }
 }
 
-#[cfg(all(windows, not(docsrs)))]
+/// Type alias for a function that converts a pointer to a filter and executes it.
+type FilterExecutor = unsafe extern "system" fn(*mut c_void, *mut RawException) -> i32;
+
+/// Internal function that converts a pointer to a filter and executes it on an exception.
+///
+/// # Arguments
+///
+/// * `filter` - A pointer to the filter to execute.
+/// * `exception` - The exception that was filled by the C stub.
+///
+/// # Returns
+///
+/// The disposition returned by the filter, as expected by the C stub.
+unsafe extern "system" fn filter_executor<G>(
+    filter: *mut c_void,
+    exception: *mut RawException,
+) -> i32
+where
+    G: FnMut(&mut Exception) -> Disposition,
+{
+    // SAFETY: The raw exception lives in the frame of `do_call_stub`, which outlives the
+    //         filter. It is neither copied, as on POSIX systems the filter runs on the signal
+    //         stack, which may be small, nor boxed, as allocating while the exception is
+    //         dispatched could reenter a heap that the faulting code left inconsistent.
+    let raw = exception;
+    let mut exception = Exception::dispatched(raw);
+
+    let disposition = match filter.cast::<G>().as_mut() {
+        Some(filter) => filter(&mut exception) as i32,
+        None => Disposition::ExecuteHandler as i32,
+    };
+
+    // A filter that swapped the exception for another one would keep a reference to the raw
+    // exception past its lifetime. This cannot unwind into the C stub, so it aborts.
+    assert!(
+        exception.is_dispatched_from(raw),
+        "the exception was moved out of its filter"
+    );
+
+    disposition
+}
+
+#[cfg(all(any(windows, unix), not(docsrs)))]
 extern "C" {
     /// External function that is responsible for handling exceptions.
     ///
@@ -40,7 +134,10 @@ extern "C" {
     ///
     /// * `proc_executor` - The wrapper function that will execute the procedure.
     /// * `proc` - A pointer to the procedure to be executed within the handled context.
+    /// * `filter_executor` - The wrapper function that will execute the filter, if any.
+    /// * `filter` - A pointer to the filter that decides whether an exception is handled.
     /// * `exception` - Where the exception information will be stored if one occurs.
+    /// * `flags` - What is captured along with the exception, such as `MS_CAPTURE_MEMORY`.
     ///
     /// # Returns
     ///
@@ -50,8 +147,116 @@ extern "C" {
     fn handler_stub(
         proc_executor: ProcExecutor,
         proc: *mut c_void,
-        exception: *mut Exception,
+        filter_executor: Option<FilterExecutor>,
+        filter: *mut c_void,
+        exception: *mut RawException,
+        flags: u32,
+    ) -> u32;
+
+    /// External function that executes a procedure, then a finalizer, which also runs when an
+    /// exception raised by the procedure is handled by an outer handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `proc_executor` - The wrapper function that will execute the procedure.
+    /// * `proc` - A pointer to the procedure to be executed.
+    /// * `finalizer_executor` - The wrapper function that will execute the finalizer.
+    /// * `finalizer` - A pointer to the finalizer to be executed.
+    #[link_name = "__microseh_FinallyStub"]
+    fn finally_stub(
+        proc_executor: ProcExecutor,
+        proc: *mut c_void,
+        finalizer_executor: ProcExecutor,
+        finalizer: *mut c_void,
+    );
+
+    /// External function that raises a software exception.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record of the exception to raise.
+    #[cfg(any(not(windows), feature = "std"))]
+    #[link_name = "__microseh_RaiseException"]
+    fn raise_exception(record: *const Record);
+
+    /// External function that raises a captured exception again.
+    ///
+    /// # Arguments
+    ///
+    /// * `exception` - The exception to raise again.
+    #[cfg(any(not(windows), feature = "std"))]
+    #[link_name = "__microseh_RethrowException"]
+    fn rethrow_exception(exception: *const RawException) -> !;
+
+    /// External function that handles exceptions like `handler_stub`, on a new stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The minimum size of the stack.
+    /// * `proc_executor` - The wrapper function that will execute the procedure.
+    /// * `proc` - A pointer to the procedure to be executed within the handled context.
+    /// * `exception` - Where the exception information will be stored if one occurs.
+    /// * `high_water_mark` - Where the amount of stack used by the procedure will be stored.
+    ///
+    /// # Returns
+    ///
+    /// * `0x0` - If the procedure executed without throwing any exceptions.
+    /// * `0x1` - If an exception occurred during the execution of the procedure.
+    /// * `0x2` - If the stack could not be created.
+    #[cfg(any(all(windows, feature = "std"), all(unix, target_env = "gnu")))]
+    #[link_name = "__microseh_StackStub"]
+    fn stack_stub(
+        size: usize,
+        proc_executor: ProcExecutor,
+        proc: *mut c_void,
+        exception: *mut RawException,
+        high_water_mark: *mut usize,
     ) -> u32;
+
+    /// External function that copies memory page by page, stopping at the first byte that faults.
+    ///
+    /// # Arguments
+    ///
+    /// * `destination` - Where the bytes are copied to.
+    /// * `source` - Where the bytes are copied from.
+    /// * `size` - The number of bytes to copy.
+    /// * `copied` - Where the number of bytes copied so far is stored, if not null.
+    #[link_name = "__microseh_CopyMemory"]
+    fn copy_memory(
+        destination: *mut c_void,
+        source: *const c_void,
+        size: usize,
+        copied: *mut usize,
+    );
+
+    /// External function that accesses a byte of every page in a range of memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The start of the range.
+    /// * `size` - The size of the range, in bytes.
+    /// * `write` - Whether the bytes are written with their own value, instead of only read.
+    #[link_name = "__microseh_ProbeMemory"]
+    fn probe_memory(address: *const c_void, size: usize, write: i32);
+}
+
+/// Fallback for the memory access stubs, which are never called when exception handling is
+/// disabled, as the orchestrators panic before running the procedure.
+#[cfg(any(not(any(windows, unix)), docsrs))]
+unsafe fn copy_memory(
+    _destination: *mut c_void,
+    _source: *const c_void,
+    _size: usize,
+    _copied: *mut usize,
+) {
+    unreachable!()
+}
+
+/// Fallback for the memory access stubs, which are never called when exception handling is
+/// disabled, as the orchestrators panic before running the procedure.
+#[cfg(any(not(any(windows, unix)), docsrs))]
+unsafe fn probe_memory(_address: *const c_void, _size: usize, _write: i32) {
+    unreachable!()
 }
 
 /// Primary execution orchestrator that calls the exception handling stub.
@@ -59,22 +264,45 @@ extern "C" {
 /// # Arguments
 ///
 /// * `proc` - The procedure to be executed within the handled context.
+/// * `filter` - The filter that decides whether an exception is handled, or `None` to handle
+///   every exception.
+/// * `flags` - What is captured along with the exception, such as `MS_CAPTURE_MEMORY`.
 ///
 /// # Returns
 ///
 /// * `Ok(())` - If the procedure executed without throwing any exceptions.
 /// * `Err(Exception)` - If an exception occurred during the execution of the procedure.
-#[cfg(all(windows, not(docsrs)))]
-fn do_call_stub<F>(mut proc: F) -> Result<(), Exception>
+#[cfg(all(any(windows, unix), not(docsrs)))]
+fn do_call_stub<F, G>(mut proc: F, filter: Option<&mut G>, flags: u32) -> Result<(), Exception>
 where
     F: FnMut(),
+    G: FnMut(&mut Exception) -> Disposition,
 {
-    let mut exception = Exception::empty();
+    // The C stub fills the whole exception when one occurs, so it is not initialized here.
+    let mut exception = MaybeUninit::<RawException>::uninit();
     let proc = &mut proc as *mut _ as *mut c_void;
 
-    match unsafe { handler_stub(proc_executor::<F>, proc, &mut exception) } {
+    let (filter_executor, filter) = match filter {
+        Some(filter) => (
+            Some(filter_executor::<G> as FilterExecutor),
+            filter as *mut G as *mut c_void,
+        ),
+        None => (None, core::ptr::null_mut()),
+    };
+
+    match unsafe {
+        handler_stub(
+            proc_executor::<F>,
+            proc,
+            filter_executor,
+            filter,
+            exception.as_mut_ptr(),
+            flags,
+        )
+    } {
         MS_SUCCEEDED => Ok(()),
-        _ => Err(exception),
+        // SAFETY: The C stub filled the exception before it was caught.
+        _ => Err(Exception::caught(unsafe { exception.assume_init() })),
     }
 }
 
@@ -84,8 +312,110 @@ where
 ///
 /// This function will always panic, notifying the user that exception handling is not
 /// available in the current build.
-#[cfg(any(not(windows), docsrs))]
-fn do_call_stub<F>(_proc: F) -> Result<(), Exception>
+#[cfg(any(not(any(windows, unix)), docsrs))]
+fn do_call_stub<F, G>(_proc: F, _filter: Option<&mut G>, _flags: u32) -> Result<(), Exception>
+where
+    F: FnMut(),
+    G: FnMut(&mut Exception) -> Disposition,
+{
+    panic!("exception handling is not available in this build of microseh")
+}
+
+/// Execution orchestrator that calls the termination handling stub.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed.
+/// * `finalizer` - The finalizer to be executed once the procedure is left.
+#[cfg(all(any(windows, unix), not(docsrs)))]
+fn do_call_finally<F, T>(mut proc: F, mut finalizer: T)
+where
+    F: FnMut(),
+    T: FnMut(),
+{
+    unsafe {
+        finally_stub(
+            proc_executor::<F>,
+            &mut proc as *mut _ as *mut c_void,
+            proc_executor::<T>,
+            &mut finalizer as *mut _ as *mut c_void,
+        )
+    }
+}
+
+/// Fallback termination orchestrator to be used when exception handling is disabled.
+///
+/// # Panics
+///
+/// This function will always panic, notifying the user that exception handling is not
+/// available in the current build.
+#[cfg(any(not(any(windows, unix)), docsrs))]
+fn do_call_finally<F, T>(_proc: F, _finalizer: T)
+where
+    F: FnMut(),
+    T: FnMut(),
+{
+    panic!("exception handling is not available in this build of microseh")
+}
+
+/// Execution orchestrator that calls the exception handling stub on a new stack.
+///
+/// # Arguments
+///
+/// * `size` - The minimum size of the stack.
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `(Ok(()), usize)` - If the procedure executed without throwing any exceptions.
+/// * `(Err(Exception), usize)` - If an exception occurred during the execution of the procedure.
+///
+/// Along with the amount of stack used by the procedure.
+///
+/// # Panics
+///
+/// If the stack could not be created.
+#[cfg(all(
+    any(all(windows, feature = "std"), all(unix, target_env = "gnu")),
+    not(docsrs)
+))]
+fn do_call_on_stack<F>(size: usize, mut proc: F) -> (Result<(), Exception>, usize)
+where
+    F: FnMut(),
+{
+    // The C stub fills the whole exception when one occurs, so it is not initialized here.
+    let mut exception = MaybeUninit::<RawException>::uninit();
+    let mut high_water_mark = 0;
+
+    let result = match unsafe {
+        stack_stub(
+            size,
+            proc_executor::<F>,
+            &mut proc as *mut _ as *mut c_void,
+            exception.as_mut_ptr(),
+            &mut high_water_mark,
+        )
+    } {
+        MS_SUCCEEDED => Ok(()),
+        MS_STACK_UNAVAILABLE => panic!("could not create a stack of {} bytes", size),
+        // SAFETY: The C stub filled the exception before it was caught.
+        _ => Err(Exception::caught(unsafe { exception.assume_init() })),
+    };
+
+    (result, high_water_mark)
+}
+
+/// Fallback stack orchestrator to be used when exception handling is disabled.
+///
+/// # Panics
+///
+/// This function will always panic, notifying the user that exception handling is not
+/// available in the current build.
+#[cfg(all(
+    any(all(windows, feature = "std"), all(unix, target_env = "gnu")),
+    docsrs
+))]
+fn do_call_on_stack<F>(_size: usize, _proc: F) -> (Result<(), Exception>, usize)
 where
     F: FnMut(),
 {
@@ -121,87 +451,1162 @@ where
 /// # Caveats
 ///
 /// If an exception occours within the procedure, resources that require cleanup via\
-/// the `Drop` trait will not be released.
+/// the `Drop` trait will not be released. Use `try_seh_cleanup` along with `unwind_on_fault`\
+/// to release them.
 ///
 /// As a rule of thumb, it's recommended not to define resources that implement\
 /// the `Drop` trait inside the procedure. Instead, allocate and manage these resources\
 /// outside, ensuring proper cleanup even if an exception occurs.
 ///
+/// Panics must not unwind out of the procedure, as they would cross the frames of the\
+/// exception handling stub, which aborts the process. Use `try_seh_unwind` to also catch them.
+///
+/// Stack overflows are caught like other exceptions, and the stack can overflow again once this\
+/// function returns. The filters then run with little stack left, on Windows, or on an\
+/// alternate signal stack, on POSIX systems.
+///
 /// # Panics
 ///
 /// If exception handling is disabled in the build, which occurs when the library is\
-/// not built on Windows with Microsoft Visual C++.
+/// built for a platform that is neither Windows nor a POSIX system.
 #[inline(always)]
 pub fn try_seh<F, R>(mut proc: F) -> Result<R, Exception>
 where
     F: FnMut() -> R,
 {
     let mut ret_val = MaybeUninit::<R>::uninit();
-    do_call_stub(|| {
-        ret_val.write(proc());
-    })
+    do_call_stub(
+        || {
+            ret_val.write(proc());
+        },
+        None::<&mut fn(&mut Exception) -> Disposition>,
+        0,
+    )
     // SAFETY: We should only reach this point if the inner closure has returned
     //         without throwing an exception, so `ret_val` should be initialized.
     .map(|_| unsafe { ret_val.assume_init() })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    const INVALID_PTR: *mut i32 = core::mem::align_of::<i32>() as _;
-
-    #[test]
-    #[cfg(feature = "std")]
-    fn all_good() {
-        let ex = try_seh(|| {
-            let _ = *Box::new(1337);
-        });
-
-        assert_eq!(ex.is_ok(), true);
-    }
-
-    #[test]
-    fn access_violation_rs() {
-        let ex = try_seh(|| unsafe {
-            INVALID_PTR.read_volatile();
-        });
-
-        assert_eq!(ex.is_err(), true);
-        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
-    }
+/// Executes the provided procedure in a context where exceptions are handled, like `try_seh`,\
+/// also capturing the memory around the point where an exception occurred.
+///
+/// The bytes at the faulting instruction, on top of the stack and around the data address\
+/// are read into the snapshots of the exception, which are empty when it is caught by the\
+/// other functions, as reading them costs a few system calls on POSIX systems.
+///
+/// On Windows, the snapshots are only captured with the `std` feature, as kernel drivers\
+/// cannot query which pages can be read, and are always empty otherwise.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions.
+/// * `Err(Exception)` - If an exception occurred during the execution of the procedure.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::try_seh_with_snapshots;
+///
+/// if let Err(e) = try_seh_with_snapshots(|| unsafe {
+///     core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+/// }) {
+///     if let Some(code) = e.code_snapshot() {
+///         println!("{:x}: {:02x?}", code.address(), code.bytes());
+///     }
+/// }
+/// ```
+///
+/// # Caveats
+///
+/// The caveats of `try_seh` apply to the procedure.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[inline(always)]
+pub fn try_seh_with_snapshots<F, R>(mut proc: F) -> Result<R, Exception>
+where
+    F: FnMut() -> R,
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+    do_call_stub(
+        || {
+            ret_val.write(proc());
+        },
+        None::<&mut fn(&mut Exception) -> Disposition>,
+        MS_CAPTURE_MEMORY,
+    )
+    // SAFETY: We should only reach this point if the inner closure has returned
+    //         without throwing an exception, so `ret_val` should be initialized.
+    .map(|_| unsafe { ret_val.assume_init() })
+}
 
-    #[test]
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    fn access_violation_asm() {
-        let ex = try_seh(|| unsafe {
-            core::arch::asm!("mov eax, DWORD PTR [0]");
-        });
+/// Executes the provided procedure in a context where both exceptions and panics are handled.
+///
+/// Panics are caught before they can unwind out of the procedure, so they never cross the\
+/// frames of the exception handling stub.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `Outcome::Ok(R)` - If the procedure executed without throwing any exceptions.
+/// * `Outcome::Fault(Exception)` - If an exception occurred during the execution of the procedure.
+/// * `Outcome::Panic(payload)` - If the procedure panicked.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_unwind, Outcome};
+///
+/// match try_seh_unwind(|| panic!("plugin failed")) {
+///     Outcome::Ok(()) => println!("plugin succeeded"),
+///     Outcome::Fault(ex) => println!("plugin crashed: {:?}", ex),
+///     Outcome::Panic(payload) => println!("plugin panicked: {:?}", payload.downcast_ref::<&str>()),
+/// }
+/// ```
+///
+/// # Caveats
+///
+/// The procedure is treated as unwind safe, so any state it shares with the caller may be\
+/// observed in an inconsistent state after a panic, as with `std::panic::AssertUnwindSafe`.
+///
+/// If an exception occurs while a panic is unwinding, for example within a `Drop`\
+/// implementation, the exception is returned and the panic payload is leaked.
+///
+/// The other caveats of `try_seh` apply to the procedure.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn try_seh_unwind<F, R>(mut proc: F) -> Outcome<R>
+where
+    F: FnMut() -> R,
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+    let mut payload = None;
+    let result = do_call_stub(
+        || match std::panic::catch_unwind(std::panic::AssertUnwindSafe(&mut proc)) {
+            Ok(value) => {
+                ret_val.write(value);
+            }
+            Err(panic) => payload = Some(panic),
+        },
+        None::<&mut fn(&mut Exception) -> Disposition>,
+        0,
+    );
 
-        assert_eq!(ex.is_err(), true);
-        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    match (result, payload) {
+        (Err(exception), _) => Outcome::Fault(exception),
+        (Ok(()), Some(payload)) => Outcome::Panic(payload),
+        // SAFETY: The inner closure returned without throwing an exception or panicking,
+        //         so `ret_val` should be initialized.
+        (Ok(()), None) => Outcome::Ok(unsafe { ret_val.assume_init() }),
     }
+}
 
-    #[test]
-    #[cfg(target_arch = "aarch64")]
-    fn access_violation_asm() {
-        let ex = try_seh(|| unsafe {
-            core::arch::asm!("ldr x0, xzr");
-        });
-
-        assert_eq!(ex.is_err(), true);
-        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+/// Executes the provided procedure in a context where exceptions are handled, running the\
+/// destructors of the values it owns when an exception is turned into an unwind by\
+/// `unwind_on_fault`.
+///
+/// The unwind starts from `unwind_on_fault`, once its exception handling stub has returned,\
+/// so it only crosses frames of Rust code, which the compiler prepared to be unwound.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions.
+/// * `Err(Exception)` - If an exception occurred during the execution of the procedure.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_cleanup, unwind_on_fault, ExceptionCode};
+///
+/// let ex = try_seh_cleanup(|| {
+///     let buffer = vec![0u8; 64];
+///     // `buffer` is dropped before the exception is returned.
+///     unwind_on_fault(|| unsafe {
+///         core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+///     });
+///     buffer.len()
+/// });
+///
+/// assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+/// ```
+///
+/// # Caveats
+///
+/// Only the exceptions that occur within `unwind_on_fault` run the destructors. The other\
+/// exceptions are caught like `try_seh` would, without releasing anything.
+///
+/// Locks held by the procedure are released by the unwind, and poisoned like on a panic.\
+/// Panics of the procedure are caught and resumed on the stack of the caller.
+///
+/// The other caveats of `try_seh_unwind` apply to the procedure.
+///
+/// Only available when panics unwind, as the exception is delivered as a panic payload.
+///
+/// # Panics
+///
+/// If the procedure panicked, with the same payload.
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(all(feature = "std", panic = "unwind"))]
+#[inline(always)]
+pub fn try_seh_cleanup<F, R>(proc: F) -> Result<R, Exception>
+where
+    F: FnMut() -> R,
+{
+    match try_seh_unwind(proc) {
+        Outcome::Ok(value) => Ok(value),
+        Outcome::Fault(exception) => Err(exception),
+        Outcome::Panic(payload) => match payload.downcast::<Exception>() {
+            Ok(exception) => Err(*exception),
+            Err(payload) => std::panic::resume_unwind(payload),
+        },
     }
+}
 
-    #[test]
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-    fn breakpoint() {
+/// Executes the provided procedure in a context where exceptions are handled, turning an\
+/// exception into an unwind that the enclosing `try_seh_cleanup` returns.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// The value returned by the procedure, if no exceptions occur.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_cleanup, unwind_on_fault};
+///
+/// let value = try_seh_cleanup(|| unwind_on_fault(|| 1337));
+/// assert_eq!(value.unwrap(), 1337);
+/// ```
+///
+/// # Caveats
+///
+/// Outside of `try_seh_cleanup`, the unwind behaves like a panic whose payload is the\
+/// `Exception`, such as the one returned by `try_seh_unwind` in `Outcome::Panic`. The panic\
+/// hook is not called for it.
+///
+/// The unwind must not cross the procedure of `try_seh`, or of any other function that does\
+/// not catch panics, between this function and `try_seh_cleanup`, as it would cross the frames\
+/// of the exception handling stub, which aborts the process.
+///
+/// The caveats of `try_seh` apply to the procedure, whose own values are not dropped.
+///
+/// Only available when panics unwind, as the exception is delivered as a panic payload.
+///
+/// # Panics
+///
+/// If an exception occurred during the execution of the procedure, with the exception as\
+/// the payload.
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(all(feature = "std", panic = "unwind"))]
+#[inline(always)]
+pub fn unwind_on_fault<F, R>(proc: F) -> R
+where
+    F: FnMut() -> R,
+{
+    match try_seh(proc) {
+        Ok(value) => value,
+        Err(exception) => std::panic::resume_unwind(Box::new(exception)),
+    }
+}
+
+/// Executes the provided procedure in a context where exceptions are handled, giving it an\
+/// arena whose allocations are all released when this function returns.
+///
+/// The arena is owned by this function rather than by the procedure, so the memory that was\
+/// allocated from it is reclaimed even if an exception interrupts the procedure.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions.
+/// * `Err(Exception)` - If an exception occurred during the execution of the procedure.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::try_seh_with_arena;
+///
+/// let ex = try_seh_with_arena(|arena| {
+///     let header = arena.alloc_slice_copy(&[0u8; 64]);
+///     header[0] = unsafe { core::ptr::read_volatile(core::mem::align_of::<u8>() as *const u8) };
+/// });
+///
+/// // `header` was released before the exception was returned.
+/// assert!(ex.is_err());
+/// ```
+///
+/// # Caveats
+///
+/// The destructors of the values stored in the arena never run, so only the memory of the\
+/// arena itself is reclaimed.
+///
+/// The other caveats of `try_seh` apply to the procedure.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn try_seh_with_arena<F, R>(mut proc: F) -> Result<R, Exception>
+where
+    F: FnMut(&Arena) -> R,
+{
+    let arena = Arena::new();
+    try_seh(|| proc(&arena))
+}
+
+/// Executes the provided procedure in a context where exceptions are handled, letting the\
+/// provided filter decide which exceptions to catch.
+///
+/// The filter is called with every exception that occurs within the procedure, before any\
+/// stack unwinding takes place, and returns the action to take:
+///
+/// * `Disposition::ExecuteHandler` - The exception is caught and returned as an error.
+/// * `Disposition::ContinueSearch` - The exception is passed on to the outer handlers, such\
+///   as an enclosing `try_seh` or an attached debugger. If nothing handles it, the process\
+///   usually terminates.
+/// * `Disposition::ContinueExecution` - Execution resumes where the exception occurred.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+/// * `filter` - The filter that decides whether an exception is caught.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions that were caught.
+/// * `Err(Exception)` - If the filter decided to catch an exception.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_filter, Disposition, ExceptionCode};
+///
+/// let ex = try_seh_filter(
+///     || unsafe {
+///         core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+///     },
+///     |ex| match ex.code() {
+///         ExceptionCode::Breakpoint => Disposition::ContinueSearch,
+///         _ => Disposition::ExecuteHandler,
+///     },
+/// );
+///
+/// assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+/// ```
+///
+/// # Caveats
+///
+/// The filter runs while the exception is being dispatched, which on POSIX systems means\
+/// inside a signal handler. It should be kept short, and avoid allocating memory or taking\
+/// locks that the faulting code may hold. Exceptions and panics within the filter are not\
+/// handled, and terminate the process.
+///
+/// The same caveats of `try_seh` apply to the procedure.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[inline(always)]
+pub fn try_seh_filter<F, G, R>(mut proc: F, mut filter: G) -> Result<R, Exception>
+where
+    F: FnMut() -> R,
+    G: FnMut(&Exception) -> Disposition,
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+    do_call_stub(
+        || {
+            ret_val.write(proc());
+        },
+        Some(&mut |exception: &mut Exception| filter(exception)),
+        0,
+    )
+    // SAFETY: We should only reach this point if the inner closure has returned
+    //         without throwing an exception, so `ret_val` should be initialized.
+    .map(|_| unsafe { ret_val.assume_init() })
+}
+
+/// Executes the provided procedure in a context where only the listed exceptions are handled.
+///
+/// Exceptions whose code is not listed keep propagating to the outer handlers, as if the\
+/// procedure was not guarded at all.
+///
+/// # Arguments
+///
+/// * `codes` - The codes of the exceptions to catch.
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions that were caught.
+/// * `Err(Exception)` - If an exception with one of the listed codes occurred.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_only, ExceptionCode};
+///
+/// let ex = try_seh_only(
+///     &[ExceptionCode::AccessViolation, ExceptionCode::InPageError],
+///     || unsafe {
+///         core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+///     },
+/// );
+///
+/// assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+/// ```
+///
+/// # Caveats
+///
+/// The same caveats of `try_seh` apply to the procedure.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[inline(always)]
+pub fn try_seh_only<F, R>(codes: &[ExceptionCode], proc: F) -> Result<R, Exception>
+where
+    F: FnMut() -> R,
+{
+    try_seh_filter(proc, |exception| match codes.contains(&exception.code()) {
+        true => Disposition::ExecuteHandler,
+        false => Disposition::ContinueSearch,
+    })
+}
+
+/// Executes the provided procedure in a context where exceptions are handled, on a new stack\
+/// that is allocated for the call and released afterwards.
+///
+/// The stack ends with a guard page, so a procedure that recurses too deeply raises a\
+/// `StackOverflow` exception instead of overflowing the stack of the caller.
+///
+/// # Arguments
+///
+/// * `size` - The minimum size of the stack, which is rounded up to whole pages and to the\
+///   minimum size supported by the system.
+/// * `proc` - The procedure to be executed within the handled context.
+///
+/// # Returns
+///
+/// The result of the procedure, along with the high-water mark of the stack.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_seh_on_stack, ExceptionCode};
+///
+/// fn depth(n: u64) -> u64 {
+///     let frame = core::hint::black_box([0u8; 256]);
+///     match n {
+///         0 => frame[0] as u64,
+///         _ => depth(n - 1) + 1,
+///     }
+/// }
+///
+/// let run = try_seh_on_stack(64 * 1024, || depth(u64::MAX));
+/// println!("used {} bytes of stack", run.high_water_mark());
+///
+/// assert_eq!(run.into_result().unwrap_err().code(), ExceptionCode::StackOverflow);
+/// ```
+///
+/// # Caveats
+///
+/// On Windows, the thread is converted to a fiber for the duration of the call if it is not\
+/// one already. On POSIX systems, the high-water mark counts the pages that are backed by\
+/// memory, and is zero if the system cannot tell.
+///
+/// Panics of the procedure are caught and resumed on the stack of the caller when the `std`\
+/// feature is enabled. Otherwise, panics must not unwind out of the procedure.
+///
+/// The other caveats of `try_seh` apply to the procedure.
+///
+/// Only available on Windows and on POSIX systems that use the GNU C library, as the other\
+/// systems provide no reliable way to switch the stack of a thread. On Windows, the `std`\
+/// feature is also required, as kernel drivers cannot use fibers.
+///
+/// # Panics
+///
+/// If the stack could not be created.
+#[cfg(any(all(windows, feature = "std"), all(unix, target_env = "gnu")))]
+#[inline(always)]
+pub fn try_seh_on_stack<F, R>(size: usize, mut proc: F) -> StackRun<R>
+where
+    F: FnMut() -> R,
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+
+    #[cfg(feature = "std")]
+    let mut panic = None;
+
+    let (result, high_water_mark) = do_call_on_stack(size, || {
+        #[cfg(feature = "std")]
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(&mut proc)) {
+            Ok(value) => {
+                ret_val.write(value);
+            }
+            Err(payload) => panic = Some(payload),
+        }
+
+        #[cfg(not(feature = "std"))]
+        ret_val.write(proc());
+    });
+
+    #[cfg(feature = "std")]
+    if let Some(payload) = panic {
+        std::panic::resume_unwind(payload);
+    }
+
+    // SAFETY: We should only reach this point if the inner closure has returned
+    //         without throwing an exception, so `ret_val` should be initialized.
+    StackRun::new(
+        result.map(|_| unsafe { ret_val.assume_init() }),
+        high_water_mark,
+    )
+}
+
+/// Executes the provided procedure in a context where exceptions are handled, letting the\
+/// provided handler change the registers and resume the execution where the exception occurred.
+///
+/// The handler works like the filter of `try_seh_filter`, and is also given a mutable view of\
+/// the registers. If it returns `Disposition::ContinueExecution`, the execution continues\
+/// with the registers as they were left by the handler, instead of leaving the procedure.\
+/// Otherwise, the changes made to the registers are discarded.
+///
+/// On Windows, the `std` feature is required, as the registers are only captured in user mode.
+///
+/// # Arguments
+///
+/// * `proc` - The procedure to be executed within the handled context.
+/// * `handler` - The handler that decides how an exception is dispatched.
+///
+/// # Returns
+///
+/// * `Ok(R)` - If the procedure executed without throwing any exceptions that were caught.
+/// * `Err(Exception)` - If the handler decided to catch an exception.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(target_arch = "x86_64")]
+/// # {
+/// use microseh::{try_seh_resume, Disposition, ExceptionCode};
+///
+/// let value = unsafe {
+///     try_seh_resume(
+///         || {
+///             let value: usize;
+///             core::arch::asm!("xor eax, eax", "ud2", out("rax") value);
+///             value
+///         },
+///         |ex, context| match ex.code() {
+///             // Skip the `ud2` instruction, returning a sentinel from it.
+///             ExceptionCode::IllegalInstruction => {
+///                 context.set_rax(0x1337);
+///                 context.skip(2);
+///                 Disposition::ContinueExecution
+///             }
+///             _ => Disposition::ExecuteHandler,
+///         },
+///     )
+/// };
+///
+/// assert_eq!(value.unwrap(), 0x1337);
+/// # }
+/// ```
+///
+/// # Safety
+///
+/// Resuming the execution with modified registers bypasses every guarantee the compiler relies\
+/// on. The caller must ensure that the changes leave the procedure in a consistent state,\
+/// for example by only changing the registers that the faulting code expects to be clobbered,\
+/// and by moving the program counter to the start of a valid instruction.
+///
+/// The caveats of `try_seh_filter` apply to the handler.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"),
+    any(not(windows), feature = "std")
+))]
+#[inline(always)]
+pub unsafe fn try_seh_resume<F, H, R>(mut proc: F, mut handler: H) -> Result<R, Exception>
+where
+    F: FnMut() -> R,
+    H: FnMut(&Exception, &mut ContextMut) -> Disposition,
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+    do_call_stub(
+        || {
+            ret_val.write(proc());
+        },
+        Some(&mut |exception: &mut Exception| {
+            let mut registers = *exception.registers();
+            let disposition = handler(exception, &mut ContextMut::new(&mut registers));
+            if disposition == Disposition::ContinueExecution {
+                exception.set_registers(registers);
+            }
+            disposition
+        }),
+        0,
+    )
+    // SAFETY: We should only reach this point if the inner closure has returned
+    //         without throwing an exception, so `ret_val` should be initialized.
+    .map(|_| ret_val.assume_init())
+}
+
+/// Executes the provided body, then the provided finalizer, like a `__try` block followed by\
+/// a `__finally` block.
+///
+/// The finalizer runs once the body is left, whether it returns, raises an exception that is\
+/// caught by an enclosing handler such as `try_seh`, or panics. When an exception is caught,\
+/// the finalizer runs before the handler returns, from the innermost region outwards.
+///
+/// # Arguments
+///
+/// * `body` - The procedure to be executed.
+/// * `finalizer` - The procedure that releases the resources acquired by the body.
+///
+/// # Returns
+///
+/// The value returned by the body.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{try_finally, try_seh};
+///
+/// let mut released = false;
+/// let ex = try_seh(|| {
+///     try_finally(
+///         || unsafe {
+///             core::ptr::read_volatile(core::mem::align_of::<i32>() as *const i32);
+///         },
+///         || released = true,
+///     )
+/// });
+///
+/// assert!(ex.is_err());
+/// assert!(released);
+/// ```
+///
+/// # Caveats
+///
+/// If no handler catches an exception raised by the body, the process terminates without\
+/// running the finalizer, as it would on Windows.
+///
+/// When an exception is caught, the finalizer runs while the exception is being dispatched,\
+/// which on POSIX systems means inside a signal handler, so the caveats of the filter of\
+/// `try_seh_filter` apply to it. Panics within the finalizer terminate the process.
+///
+/// Panics of the body are caught and resumed after the finalizer runs when the `std`\
+/// feature is enabled. Otherwise, panics must not unwind out of the body.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[inline(always)]
+pub fn try_finally<F, T, R>(mut body: F, finalizer: T) -> R
+where
+    F: FnMut() -> R,
+    T: FnOnce(),
+{
+    let mut ret_val = MaybeUninit::<R>::uninit();
+    let mut finalizer = Some(finalizer);
+
+    #[cfg(feature = "std")]
+    let mut panic = None;
+
+    do_call_finally(
+        || {
+            #[cfg(feature = "std")]
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(&mut body)) {
+                Ok(value) => {
+                    ret_val.write(value);
+                }
+                Err(payload) => panic = Some(payload),
+            }
+
+            #[cfg(not(feature = "std"))]
+            ret_val.write(body());
+        },
+        || {
+            if let Some(finalizer) = finalizer.take() {
+                finalizer();
+            }
+        },
+    );
+
+    #[cfg(feature = "std")]
+    if let Some(payload) = panic {
+        std::panic::resume_unwind(payload);
+    }
+
+    // SAFETY: We should only reach this point if the body has returned without throwing an
+    //         exception or panicking, so `ret_val` should be initialized.
+    unsafe { ret_val.assume_init() }
+}
+
+/// Raises a software exception, which is handled like any other exception by the nearest\
+/// enclosing handler, such as `try_seh`.
+///
+/// On Windows, this calls `RaiseException`. On POSIX systems, the exception is delivered as the\
+/// `SIGRTMIN + 3` signal to the current thread, carrying the given record, and the\
+/// `ExceptionFlags::SOFTWARE_ORIGINATE` flag is added to it. Systems without real-time\
+/// signals use `SIGTRAP` instead, which debuggers usually stop on and discard.
+///
+/// On Windows, the `std` feature is required, as kernel drivers cannot call `RaiseException`.
+///
+/// # Arguments
+///
+/// * `code` - The code of the exception.
+/// * `flags` - The flags of the exception. Only `ExceptionFlags::NONCONTINUABLE` affects how\
+///   the exception is dispatched.
+/// * `parameters` - The parameters of the exception, of which only the first 15 are kept.
+///
+/// # Returns
+///
+/// Only if a filter continued the execution after the exception. If the exception is\
+/// noncontinuable, a `NoncontinuableException` is raised instead.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{raise, try_seh, ExceptionCode, ExceptionFlags};
+///
+/// let ex = try_seh(|| unsafe {
+///     raise(ExceptionCode::Other(0xE0001234), ExceptionFlags::default(), &[1, 2, 3]);
+/// });
+///
+/// let ex = ex.unwrap_err();
+/// assert_eq!(ex.code(), ExceptionCode::Other(0xE0001234));
+/// assert_eq!(ex.parameters(), &[1, 2, 3]);
+/// ```
+///
+/// # Safety
+///
+/// The exception leaves the frames between this function and the handler that catches it\
+/// without running any destructor, like a hardware exception would. If no handler catches\
+/// it, the process is terminated.
+///
+/// # Panics
+///
+/// If exception handling is disabled in the build, which occurs when the library is\
+/// built for a platform that is neither Windows nor a POSIX system.
+#[cfg(any(not(windows), feature = "std"))]
+#[inline(never)]
+pub unsafe fn raise(code: ExceptionCode, flags: ExceptionFlags, parameters: &[usize]) {
+    #[cfg(all(any(windows, unix), not(docsrs)))]
+    {
+        let record = Record::new(code, flags, parameters);
+        raise_exception(&record);
+    }
+
+    #[cfg(any(not(any(windows, unix)), docsrs))]
+    {
+        let _ = (code, flags, parameters);
+        panic!("exception handling is not available in this build of microseh")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+
+    use core::cell::Cell;
+
+    const INVALID_PTR: *mut i32 = core::mem::align_of::<i32>() as _;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn all_good() {
+        let ex = try_seh(|| {
+            let _ = *Box::new(1337);
+        });
+
+        assert_eq!(ex.is_ok(), true);
+    }
+
+    #[test]
+    fn access_violation_rs() {
+        let ex = try_seh(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        assert_eq!(ex.is_err(), true);
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    fn access_violation_address() {
+        let ex = try_seh(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        let ex = ex.unwrap_err();
+        assert_ne!(ex.address(), 0);
+        assert_eq!(ex.data_address(), Some(INVALID_PTR as usize));
+    }
+
+    #[test]
+    fn access_violation_kind() {
+        let ex = try_seh(|| unsafe {
+            INVALID_PTR.write_volatile(0);
+        });
+
+        assert_eq!(
+            ex.unwrap_err().kind(),
+            ExceptionKind::AccessViolation {
+                access: AccessKind::Write,
+                address: INVALID_PTR as usize,
+            }
+        );
+    }
+
+    #[test]
+    fn access_violation_record() {
+        let ex = try_seh(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        let ex = ex.unwrap_err();
+        assert!(ex.flags().is_empty());
+        assert_eq!(ex.parameters(), &[0, INVALID_PTR as usize]);
+
+        #[cfg(unix)]
+        assert_eq!(ex.signal_info().address(), INVALID_PTR as usize);
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn memory_snapshots() {
+        static READ_ONLY: [u8; 64] = {
+            let mut bytes = [0; 64];
+            let mut i = 0;
+            while i < bytes.len() {
+                bytes[i] = i as u8;
+                i += 1;
+            }
+            bytes
+        };
+
+        let target = READ_ONLY[32..].as_ptr() as *mut u8;
+        let ex = try_seh_with_snapshots(|| unsafe {
+            target.write_volatile(0xFF);
+        });
+
+        let ex = ex.unwrap_err();
+        let data = ex.data_snapshot().unwrap();
+        assert_eq!(data.address(), READ_ONLY.as_ptr() as usize);
+        assert_eq!(data.bytes(), &READ_ONLY);
+        assert!(data.contains(target as usize));
+
+        let code = ex.code_snapshot().unwrap();
+        assert_eq!(code.address(), ex.address());
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+        assert_eq!(ex.stack_snapshot().unwrap().address(), ex.registers().sp());
+    }
+
+    #[test]
+    fn memory_snapshots_unreadable() {
+        let ex = try_seh_with_snapshots(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        assert!(ex.unwrap_err().data_snapshot().is_none());
+    }
+
+    #[test]
+    fn memory_snapshots_not_requested() {
+        let ex = try_seh(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        let ex = ex.unwrap_err();
+        assert!(ex.code_snapshot().is_none());
+        assert!(ex.stack_snapshot().is_none());
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn access_violation_asm() {
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!("mov eax, DWORD PTR [0]");
+        });
+
+        assert_eq!(ex.is_err(), true);
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn access_violation_asm() {
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!("ldr x0, xzr");
+        });
+
+        assert_eq!(ex.is_err(), true);
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn breakpoint() {
         let ex = try_seh(|| unsafe {
             core::arch::asm!("int3");
         });
 
-        assert_eq!(ex.is_err(), true);
-        assert_eq!(ex.unwrap_err().code(), ExceptionCode::Breakpoint);
+        assert_eq!(ex.is_err(), true);
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::Breakpoint);
+    }
+
+    #[allow(unconditional_recursion)]
+    fn overflow(depth: usize) -> usize {
+        let frame = core::hint::black_box([depth as u8; 1024]);
+        overflow(depth + 1).wrapping_add(frame[0] as usize)
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    fn stack_overflow() {
+        for _ in 0..3 {
+            let ex = try_seh(|| overflow(0));
+            assert_eq!(ex.unwrap_err().code(), ExceptionCode::StackOverflow);
+        }
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn stack_overflow_foreign_thread() {
+        extern "C" {
+            fn pthread_create(
+                thread: *mut usize,
+                attr: *const c_void,
+                start: extern "C" fn(*mut c_void) -> *mut c_void,
+                arg: *mut c_void,
+            ) -> i32;
+            fn pthread_join(thread: usize, ret: *mut *mut c_void) -> i32;
+        }
+
+        // Threads that are not spawned by Rust have no alternate signal stack of their own.
+        extern "C" fn start(_: *mut c_void) -> *mut c_void {
+            let caught = (0..3).all(|_| {
+                try_seh(|| overflow(0)).map_err(|ex| ex.code()) == Err(ExceptionCode::StackOverflow)
+            });
+
+            caught as usize as *mut c_void
+        }
+
+        let mut thread = 0;
+        let mut caught = core::ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                pthread_create(&mut thread, core::ptr::null(), start, core::ptr::null_mut()),
+                0
+            );
+            assert_eq!(pthread_join(thread, &mut caught), 0);
+        }
+
+        assert_eq!(caught as usize, 1);
+    }
+
+    fn recurse(depth: usize) -> usize {
+        let frame = core::hint::black_box([depth as u8; 1024]);
+        match depth {
+            0 => frame[0] as usize,
+            _ => recurse(depth - 1).wrapping_add(frame[0] as usize),
+        }
+    }
+
+    #[test]
+    #[cfg(any(
+        all(windows, feature = "std"),
+        all(target_os = "linux", target_env = "gnu")
+    ))]
+    fn on_stack_ok() {
+        let shallow = try_seh_on_stack(0x100000, || recurse(16));
+        let deep = try_seh_on_stack(0x100000, || recurse(256));
+
+        assert!(shallow.is_ok() && deep.is_ok());
+        assert!(shallow.high_water_mark() > 0);
+        assert!(deep.high_water_mark() >= 256 * 1024);
+        assert!(deep.high_water_mark() <= 0x100000);
+        assert!(deep.high_water_mark() > shallow.high_water_mark());
+    }
+
+    #[test]
+    #[cfg(any(
+        all(windows, feature = "std"),
+        all(target_os = "linux", target_env = "gnu")
+    ))]
+    fn on_stack_overflow() {
+        for _ in 0..3 {
+            let run = try_seh_on_stack(0x40000, || overflow(0));
+            assert!(run.high_water_mark() >= 0x30000);
+            assert_eq!(
+                run.into_result().unwrap_err().code(),
+                ExceptionCode::StackOverflow
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(any(
+        all(windows, feature = "std"),
+        all(target_os = "linux", target_env = "gnu")
+    ))]
+    fn on_stack_nested() {
+        let run = try_seh_on_stack(0x40000, || {
+            try_seh_on_stack(0x40000, || unsafe { INVALID_PTR.read_volatile() }).into_result()
+        });
+
+        let inner = run.into_result().unwrap();
+        assert_eq!(inner.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "std",
+        any(windows, all(target_os = "linux", target_env = "gnu"))
+    ))]
+    #[should_panic(expected = "evaluator failed")]
+    fn on_stack_panic() {
+        try_seh_on_stack(0x40000, || -> i32 { panic!("evaluator failed") });
+    }
+
+    #[test]
+    fn filter_execute_handler() {
+        let mut calls = 0;
+        let ex = try_seh_filter(
+            || unsafe {
+                INVALID_PTR.read_volatile();
+            },
+            |ex| {
+                calls += 1;
+                assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+                Disposition::ExecuteHandler
+            },
+        );
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn filter_continue_search() {
+        let mut reached = false;
+        let ex = try_seh(|| {
+            let _ = try_seh_filter(
+                || unsafe {
+                    INVALID_PTR.read_volatile();
+                },
+                |_| Disposition::ContinueSearch,
+            );
+            reached = true;
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert!(!reached);
+    }
+
+    #[test]
+    fn only_listed() {
+        let ex = try_seh_only(
+            &[ExceptionCode::Breakpoint, ExceptionCode::AccessViolation],
+            || unsafe {
+                INVALID_PTR.read_volatile();
+            },
+        );
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    fn only_unlisted() {
+        let mut reached = false;
+        let ex = try_seh(|| {
+            let _ = try_seh_only(&[ExceptionCode::InPageError], || unsafe {
+                INVALID_PTR.read_volatile();
+            });
+            reached = true;
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert!(!reached);
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn only_unknown_code() {
+        let ex = try_seh(|| {
+            let _ = try_seh_only(&[ExceptionCode::AccessViolation], || unsafe {
+                raise(
+                    ExceptionCode::Other(0xE0001234),
+                    ExceptionFlags::default(),
+                    &[],
+                );
+            });
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::Other(0xE0001234));
+    }
+
+    #[test]
+    #[cfg(all(unix, any(target_arch = "x86", target_arch = "x86_64")))]
+    fn filter_continue_execution() {
+        // The program counter is left past `int3` on POSIX systems, so execution can continue.
+        let ret = try_seh_filter(
+            || unsafe {
+                core::arch::asm!("int3");
+                1337
+            },
+            |ex| match ex.code() {
+                ExceptionCode::Breakpoint => Disposition::ContinueExecution,
+                _ => Disposition::ExecuteHandler,
+            },
+        );
+
+        assert_eq!(ret.unwrap(), 1337);
     }
 
     #[test]
@@ -230,7 +1635,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(target_arch = "x86")]
+    #[cfg(all(target_arch = "x86", any(not(windows), feature = "std")))]
     fn reg_state_check() {
         let ex = try_seh(|| unsafe {
             core::arch::asm!("mov eax, 0xbadc0de", "ud2");
@@ -246,7 +1651,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", any(not(windows), feature = "std")))]
     fn reg_state_check() {
         let ex = try_seh(|| unsafe {
             core::arch::asm!("mov rax, 0xbadc0debabefffff", "ud2");
@@ -262,7 +1667,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(target_arch = "aarch64")]
+    #[cfg(all(target_arch = "aarch64", any(not(windows), feature = "std")))]
     fn reg_state_check() {
         let ex = try_seh(|| unsafe {
             core::arch::asm!(
@@ -283,6 +1688,558 @@ mod tests {
         assert_eq!(ex.unwrap_err().registers().x0(), 0xbadc0debabefffff);
     }
 
+    #[test]
+    #[cfg(all(target_arch = "x86", any(not(windows), feature = "std")))]
+    fn fpu_state_check() {
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!("mov eax, 0xbadc0de", "movd xmm0, eax", "ud2", out("xmm0") _);
+        });
+
+        let ex = ex.unwrap_err();
+        let fpu = ex.fpu().unwrap();
+        assert_eq!(fpu.xmm(0), Some(0xbadc0de));
+        // All the SSE exceptions are masked by default.
+        assert_eq!(fpu.mxcsr() & 0x1F80, 0x1F80);
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86_64", any(not(windows), feature = "std")))]
+    fn fpu_state_check() {
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!(
+                "mov rax, 0xbadc0debabefffff",
+                "movq xmm0, rax",
+                "ud2",
+                out("xmm0") _
+            );
+        });
+
+        let ex = ex.unwrap_err();
+        let fpu = ex.fpu().unwrap();
+        assert_eq!(fpu.xmm(0), Some(0xbadc0debabefffff));
+        // All the SSE exceptions are masked by default.
+        assert_eq!(fpu.mxcsr() & 0x1F80, 0x1F80);
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    fn avx_state_check() {
+        if !std::is_x86_feature_detected!("avx") {
+            return;
+        }
+
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!("vcmpps ymm1, ymm1, ymm1, 15", "ud2", out("xmm1") _);
+        });
+
+        let ex = ex.unwrap_err();
+        let fpu = ex.fpu().unwrap();
+        assert!(fpu.has_avx());
+        assert_eq!(fpu.ymm(1), Some([u128::MAX, u128::MAX]));
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "aarch64", any(not(windows), feature = "std")))]
+    fn fpu_state_check() {
+        let ex = try_seh(|| unsafe {
+            core::arch::asm!(
+                "movz x0, #0xbadc, LSL 48",
+                "movk x0, #0x0deb, LSL 32",
+                "movk x0, #0xabef, LSL 16",
+                "movk x0, #0xffff",
+                "fmov d0, x0",
+                "udf #0",
+                out("v0") _
+            );
+        });
+
+        let ex = ex.unwrap_err();
+        let fpu = ex.fpu().unwrap();
+        assert_eq!(fpu.v(0), Some(0xbadc0debabefffff));
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86", any(not(windows), feature = "std")))]
+    fn resume_skip() {
+        let ret = unsafe {
+            try_seh_resume(
+                || {
+                    let value: usize;
+                    core::arch::asm!("xor eax, eax", "ud2", out("eax") value);
+                    value
+                },
+                |ex, context| {
+                    assert_eq!(ex.code(), ExceptionCode::IllegalInstruction);
+                    context.set_eax(0xbadc0de);
+                    context.skip(2);
+                    Disposition::ContinueExecution
+                },
+            )
+        };
+
+        assert_eq!(ret.unwrap(), 0xbadc0de);
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "x86_64", any(not(windows), feature = "std")))]
+    fn resume_skip() {
+        let ret = unsafe {
+            try_seh_resume(
+                || {
+                    let value: usize;
+                    core::arch::asm!("xor eax, eax", "ud2", out("rax") value);
+                    value
+                },
+                |ex, context| {
+                    assert_eq!(ex.code(), ExceptionCode::IllegalInstruction);
+                    context.set_rax(0xbadc0debabefffff);
+                    context.skip(2);
+                    Disposition::ContinueExecution
+                },
+            )
+        };
+
+        assert_eq!(ret.unwrap(), 0xbadc0debabefffff);
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "aarch64", any(not(windows), feature = "std")))]
+    fn resume_skip() {
+        let ret = unsafe {
+            try_seh_resume(
+                || {
+                    let value: usize;
+                    core::arch::asm!("mov x0, #0", "udf #0", out("x0") value);
+                    value
+                },
+                |ex, context| {
+                    assert_eq!(ex.code(), ExceptionCode::IllegalInstruction);
+                    context.set_x0(0xbadc0debabefffff);
+                    context.skip(4);
+                    Disposition::ContinueExecution
+                },
+            )
+        };
+
+        assert_eq!(ret.unwrap(), 0xbadc0debabefffff);
+    }
+
+    #[test]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"),
+        any(not(windows), feature = "std")
+    ))]
+    fn resume_discard() {
+        let pc = Cell::new(0);
+        let ret = unsafe {
+            try_seh_resume(
+                || {
+                    INVALID_PTR.read_volatile();
+                },
+                |ex, context| {
+                    pc.set(ex.registers().pc());
+                    context.skip(4);
+                    Disposition::ExecuteHandler
+                },
+            )
+        };
+
+        let ex = ret.unwrap_err();
+        assert_eq!(ex.registers().pc(), pc.get());
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn raise_software() {
+        let ex = try_seh(|| unsafe {
+            raise(
+                ExceptionCode::Other(0xE0001234),
+                ExceptionFlags::NONCONTINUABLE,
+                &[1, 2, 3],
+            );
+        });
+
+        let ex = ex.unwrap_err();
+        assert_eq!(ex.code(), ExceptionCode::Other(0xE0001234));
+        assert_eq!(ex.parameters(), &[1, 2, 3]);
+        assert!(ex.flags().contains(ExceptionFlags::NONCONTINUABLE));
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn raise_continue() {
+        let ret = try_seh_filter(
+            || unsafe {
+                raise(
+                    ExceptionCode::Other(0xE0001234),
+                    ExceptionFlags::default(),
+                    &[],
+                );
+                1337
+            },
+            |ex| match ex.code() {
+                ExceptionCode::Other(0xE0001234) => Disposition::ContinueExecution,
+                _ => Disposition::ExecuteHandler,
+            },
+        );
+
+        assert_eq!(ret.unwrap(), 1337);
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn raise_noncontinuable() {
+        let mut calls = 0;
+        let ex = try_seh_filter(
+            || unsafe {
+                raise(
+                    ExceptionCode::Other(0xE0001234),
+                    ExceptionFlags::NONCONTINUABLE,
+                    &[],
+                );
+            },
+            |_| {
+                calls += 1;
+                match calls {
+                    1 => Disposition::ContinueExecution,
+                    _ => Disposition::ExecuteHandler,
+                }
+            },
+        );
+
+        assert_eq!(
+            ex.unwrap_err().code(),
+            ExceptionCode::NonContinuableException
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn raise_signal() {
+        // Debuggers stop on `SIGTRAP` and discard it, so software exceptions use another signal.
+        const SIGTRAP: i32 = 5;
+
+        let ex = try_seh(|| unsafe {
+            raise(
+                ExceptionCode::Other(0xE0001234),
+                ExceptionFlags::default(),
+                &[],
+            );
+        });
+
+        let signal = ex.unwrap_err().signal_info().signal();
+        assert_ne!(signal, 0);
+        assert_ne!(signal, SIGTRAP);
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn rethrow_outer() {
+        let ex = try_seh(|| {
+            let ex = try_seh(|| unsafe {
+                raise(
+                    ExceptionCode::Other(0xE0001234),
+                    ExceptionFlags::default(),
+                    &[1, 2, 3],
+                );
+            });
+
+            unsafe { ex.unwrap_err().rethrow() }
+        });
+
+        let ex = ex.unwrap_err();
+        assert_eq!(ex.code(), ExceptionCode::Other(0xE0001234));
+        assert_eq!(ex.parameters(), &[1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(any(not(windows), feature = "std"))]
+    fn rethrow_address() {
+        let mut inner = None;
+        let ex = try_seh(|| {
+            let ex = try_seh(|| unsafe {
+                core::ptr::read_volatile(INVALID_PTR as *const i32);
+            })
+            .unwrap_err();
+
+            inner = Some((ex.address(), ex.data_address()));
+            unsafe { ex.rethrow() }
+        });
+
+        let ex = ex.unwrap_err();
+        let (address, data_address) = inner.unwrap();
+        assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+        assert_eq!(ex.address(), address);
+        assert_eq!(ex.data_address(), data_address);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn unwind_ok() {
+        let outcome = try_seh_unwind(|| 1337);
+        assert_eq!(outcome.ok(), Some(1337));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn unwind_fault() {
+        let outcome = try_seh_unwind(|| unsafe {
+            INVALID_PTR.read_volatile();
+        });
+
+        let ex = outcome.fault().unwrap();
+        assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn unwind_panic() {
+        let outcome = try_seh_unwind(|| -> i32 { panic!("boom") });
+
+        let payload = outcome.panic().unwrap();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn unwind_nested() {
+        let outcome = try_seh_unwind(|| {
+            try_seh_unwind(|| -> i32 { panic!("inner") }).is_panic()
+                && try_seh(|| unsafe { INVALID_PTR.read_volatile() }).is_err()
+        });
+
+        assert_eq!(outcome.ok(), Some(true));
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", panic = "unwind"))]
+    fn cleanup_ok() {
+        let value = try_seh_cleanup(|| unwind_on_fault(|| 1337) + 1);
+        assert_eq!(value.unwrap(), 1338);
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", panic = "unwind"))]
+    fn cleanup_drops() {
+        struct Guard<'a>(&'a Cell<usize>);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let mutex = std::sync::Mutex::new(0);
+        let ex = try_seh_cleanup(|| {
+            let _guard = Guard(&drops);
+            let _lock = mutex.lock();
+            unwind_on_fault(|| unsafe { INVALID_PTR.read_volatile() })
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert_eq!(drops.get(), 1);
+        assert!(!matches!(
+            mutex.try_lock(),
+            Err(std::sync::TryLockError::WouldBlock)
+        ));
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", panic = "unwind"))]
+    fn cleanup_outside_guard() {
+        let ex = try_seh_cleanup(|| unsafe { INVALID_PTR.read_volatile() });
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", panic = "unwind"))]
+    fn cleanup_unwind_payload() {
+        let outcome = try_seh_unwind(|| unwind_on_fault(|| unsafe { INVALID_PTR.read_volatile() }));
+
+        let payload = outcome.panic().unwrap();
+        let ex = payload.downcast_ref::<Exception>().unwrap();
+        assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", panic = "unwind"))]
+    #[should_panic(expected = "evaluator failed")]
+    fn cleanup_panic() {
+        let _ = try_seh_cleanup(|| -> i32 { panic!("evaluator failed") });
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn arena_ok() {
+        let len = try_seh_with_arena(|arena| {
+            let name = arena.alloc_str("microseh");
+            let value = arena.alloc(1337u32);
+            name.len() + *value as usize
+        });
+
+        assert_eq!(len.unwrap(), 1345);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn arena_fault() {
+        let mut allocated = 0;
+        let ex = try_seh_with_arena(|arena| unsafe {
+            let buffer = arena.alloc_slice_copy(&[0u8; 0x10000]);
+            allocated = arena.allocated_bytes();
+            buffer[0] = INVALID_PTR.cast::<u8>().read_volatile();
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert_eq!(allocated, 0x10000);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn arena_fault_released() {
+        let live = counting::live_bytes();
+        for _ in 0..64 {
+            let ex = try_seh_with_arena(|arena| unsafe {
+                arena.alloc_slice_copy(&[0u8; 0x10000]);
+                arena.alloc_str("interrupted");
+                INVALID_PTR.read_volatile();
+            });
+
+            assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        }
+
+        assert_eq!(counting::live_bytes(), live);
+    }
+
+    /// Global allocator of the tests, which counts the bytes that are live on each thread, so
+    /// that the tests running in parallel do not affect each other.
+    #[cfg(feature = "std")]
+    mod counting {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        struct Counting;
+
+        thread_local! {
+            static LIVE: Cell<isize> = const { Cell::new(0) };
+        }
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = LIVE.try_with(|live| live.set(live.get() + layout.size() as isize));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                let _ = LIVE.try_with(|live| live.set(live.get() - layout.size() as isize));
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static ALLOCATOR: Counting = Counting;
+
+        /// # Returns
+        ///
+        /// The number of bytes allocated by the current thread that were not released yet.
+        pub(super) fn live_bytes() -> isize {
+            LIVE.with(Cell::get)
+        }
+    }
+
+    #[test]
+    fn finally_return() {
+        let mut finalized = false;
+        let ret = try_finally(|| 1337, || finalized = true);
+
+        assert_eq!(ret, 1337);
+        assert!(finalized);
+    }
+
+    #[test]
+    fn finally_exception() {
+        let order = [Cell::new(0), Cell::new(0)];
+        let count = Cell::new(0);
+        let finalize = |index: usize| {
+            count.set(count.get() + 1);
+            order[index].set(count.get());
+        };
+
+        let ex = try_seh(|| {
+            try_finally(
+                || try_finally(|| unsafe { INVALID_PTR.read_volatile() }, || finalize(0)),
+                || finalize(1),
+            )
+        });
+
+        assert_eq!(ex.unwrap_err().code(), ExceptionCode::AccessViolation);
+        assert_eq!(order[0].get(), 1);
+        assert_eq!(order[1].get(), 2);
+    }
+
+    #[test]
+    fn finally_caught_inside() {
+        let count = Cell::new(0);
+        let caught = try_finally(
+            || try_seh(|| unsafe { INVALID_PTR.read_volatile() }).is_err(),
+            || count.set(count.get() + 1),
+        );
+
+        assert!(caught);
+        assert_eq!(count.get(), 1);
+
+        // The region was left, so later exceptions do not run the finalizer again.
+        assert!(try_seh(|| unsafe { INVALID_PTR.read_volatile() }).is_err());
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn finally_panic() {
+        let mut finalized = false;
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            try_finally(
+                || std::panic::resume_unwind(Box::new(7)),
+                || finalized = true,
+            )
+        }));
+
+        assert_eq!(payload.unwrap_err().downcast_ref::<i32>(), Some(&7));
+        assert!(finalized);
+    }
+
+    #[test]
+    fn code_conversions() {
+        assert_eq!(
+            ExceptionCode::from(0xC0000005),
+            ExceptionCode::AccessViolation
+        );
+        assert_eq!(u32::from(ExceptionCode::AccessViolation), 0xC0000005);
+
+        let unknown = ExceptionCode::from(0xE0001234);
+        assert_eq!(unknown, ExceptionCode::Other(0xE0001234));
+        assert_eq!(unknown.raw(), 0xE0001234);
+
+        assert_eq!(
+            ExceptionCode::from(0xC0000409),
+            ExceptionCode::StackBufferOverrun
+        );
+        assert_eq!(ExceptionCode::from(0xE06D7363), ExceptionCode::CppException);
+
+        // Known codes compare equal regardless of the variant that holds them.
+        assert_eq!(ExceptionCode::Other(0x80000003), ExceptionCode::Breakpoint);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            ExceptionCode::Other(0x80000003).to_string(),
+            ExceptionCode::Breakpoint.to_string()
+        );
+        assert!(matches!(
+            ExceptionCode::from_raw(0x80000003),
+            ExceptionCode::Breakpoint
+        ));
+    }
+
     #[test]
     fn ret_vals() {
         let a = try_seh(|| 1337);
@@ -0,0 +1,91 @@
+#include <string.h>
+
+#include "stub.h"
+
+#if defined(_MSC_VER)
+#include <intrin.h>
+#endif
+
+// Smallest page size of the supported platforms, so that touching a byte every this many bytes
+// touches every page of a range, and that a range within such a step lies in a single page.
+#define PROBE_STEP 0x1000
+
+// Writes the byte with its own value, without losing the writes of other threads.
+static void TouchByte(volatile uint8_t* Address)
+{
+#if defined(_MSC_VER)
+    char Value = *(volatile char*)Address;
+    char Current;
+
+    while ((Current = _InterlockedCompareExchange8((volatile char*)Address, Value, Value)) != Value)
+    {
+        Value = Current;
+    }
+#else
+    uint8_t Value = *Address;
+
+    while (!__atomic_compare_exchange_n((uint8_t*)Address, &Value, Value, 0, __ATOMIC_RELAXED, __ATOMIC_RELAXED))
+    {
+    }
+#endif
+}
+
+void __microseh_CopyMemory(void* Destination, const void* Source, size_t Size, volatile size_t* Copied)
+{
+    uint8_t* To = (uint8_t*)Destination;
+    const uint8_t* From = (const uint8_t*)Source;
+    size_t Offset = 0;
+
+    while (Offset < Size)
+    {
+        // The chunk ends at the next page boundary of either range, so its pages are either
+        // accessible or not, and only its first byte can fault.
+        size_t Chunk = PROBE_STEP - ((uintptr_t)(From + Offset) & (PROBE_STEP - 1));
+        size_t DestinationChunk = PROBE_STEP - ((uintptr_t)(To + Offset) & (PROBE_STEP - 1));
+
+        if (Chunk > DestinationChunk)
+        {
+            Chunk = DestinationChunk;
+        }
+
+        if (Chunk > Size - Offset)
+        {
+            Chunk = Size - Offset;
+        }
+
+        ((volatile uint8_t*)To)[Offset] = ((const volatile uint8_t*)From)[Offset];
+        if (Copied != NULL)
+        {
+            *Copied = Offset + 1;
+        }
+
+        memcpy(To + Offset + 1, From + Offset + 1, Chunk - 1);
+        Offset += Chunk;
+
+        if (Copied != NULL)
+        {
+            *Copied = Offset;
+        }
+    }
+}
+
+void __microseh_ProbeMemory(const void* Address, size_t Size, int32_t Write)
+{
+    volatile uint8_t* Bytes = (volatile uint8_t*)Address;
+    size_t Offset = 0;
+
+    while (Offset < Size)
+    {
+        if (Write)
+        {
+            TouchByte(Bytes + Offset);
+        }
+        else
+        {
+            (void)Bytes[Offset];
+        }
+
+        // The next byte touched is the first one of the following page.
+        Offset = (((uintptr_t)Address + Offset) | (PROBE_STEP - 1)) + 1 - (uintptr_t)Address;
+    }
+}
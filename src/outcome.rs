@@ -0,0 +1,71 @@
+use std::any::Any;
+
+use crate::Exception;
+
+/// Result of a procedure executed by `try_seh_unwind`, which can either return, raise a\
+/// hardware exception or panic.
+#[derive(Debug)]
+pub enum Outcome<R> {
+    /// The procedure returned a value.
+    Ok(R),
+    /// The procedure raised an exception.
+    Fault(Exception),
+    /// The procedure panicked, with the payload that was given to the panic.
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+impl<R> Outcome<R> {
+    /// # Returns
+    ///
+    /// Whether the procedure returned a value.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok(_))
+    }
+
+    /// # Returns
+    ///
+    /// Whether the procedure raised an exception.
+    pub fn is_fault(&self) -> bool {
+        matches!(self, Self::Fault(_))
+    }
+
+    /// # Returns
+    ///
+    /// Whether the procedure panicked.
+    pub fn is_panic(&self) -> bool {
+        matches!(self, Self::Panic(_))
+    }
+
+    /// # Returns
+    ///
+    /// * `Some(R)` - The value returned by the procedure.
+    /// * `None` - If the procedure raised an exception or panicked.
+    pub fn ok(self) -> Option<R> {
+        match self {
+            Self::Ok(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// * `Some(Exception)` - The exception raised by the procedure.
+    /// * `None` - If the procedure returned a value or panicked.
+    pub fn fault(self) -> Option<Exception> {
+        match self {
+            Self::Fault(exception) => Some(exception),
+            _ => None,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// * `Some(payload)` - The payload of the panic.
+    /// * `None` - If the procedure returned a value or raised an exception.
+    pub fn panic(self) -> Option<Box<dyn Any + Send + 'static>> {
+        match self {
+            Self::Panic(payload) => Some(payload),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,520 @@
+//! Accesses to memory that may not be valid, such as pointers received from untrusted code.
+//!
+//! Every access is made within `try_seh`, so an invalid address is reported as an exception\
+//! instead of crashing the process.
+
+use core::{ffi::c_void, mem::MaybeUninit};
+
+use crate::{copy_memory, probe_memory, try_seh, Exception};
+
+/// Smallest page size of the supported platforms. Reads that do not cross a multiple of it\
+/// either fault entirely or not at all.
+const PAGE_SIZE: usize = 0x1000;
+
+/// Reads a value from memory that may not be readable.
+///
+/// # Arguments
+///
+/// * `ptr` - The address of the value, which does not need to be aligned.
+///
+/// # Returns
+///
+/// * `Ok(T)` - The value that was read.
+/// * `Err(Exception)` - If the memory could not be read.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::{probe, ExceptionCode};
+///
+/// let value = 0x1337u32;
+/// assert_eq!(unsafe { probe::read(&value) }.unwrap(), 0x1337);
+///
+/// let ex = unsafe { probe::read(core::ptr::null::<u32>()) }.unwrap_err();
+/// assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+/// ```
+///
+/// # Safety
+///
+/// If the memory is readable, it must hold a valid value of type `T`.
+pub unsafe fn read<T: Copy>(ptr: *const T) -> Result<T, Exception> {
+    let mut value = MaybeUninit::<T>::uninit();
+    try_seh(|| unsafe {
+        copy_memory(
+            value.as_mut_ptr().cast(),
+            ptr.cast(),
+            core::mem::size_of::<T>(),
+            core::ptr::null_mut(),
+        )
+    })?;
+
+    // SAFETY: Every byte of the value was copied from memory that holds a valid `T`.
+    Ok(value.assume_init())
+}
+
+/// Writes a value to memory that may not be writable.
+///
+/// # Arguments
+///
+/// * `ptr` - The address of the value, which does not need to be aligned.
+/// * `value` - The value to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the value was written.
+/// * `Err(Exception)` - If the memory could not be written.
+///
+/// # Safety
+///
+/// The memory, if writable, must not be in use by code that relies on its contents, as\
+/// they are overwritten without any synchronization.
+///
+/// If an exception occurs, part of the value may already have been written.
+pub unsafe fn write<T: Copy>(ptr: *mut T, value: T) -> Result<(), Exception> {
+    try_seh(|| unsafe {
+        copy_memory(
+            ptr.cast(),
+            (&value as *const T).cast(),
+            core::mem::size_of::<T>(),
+            core::ptr::null_mut(),
+        )
+    })
+}
+
+/// Copies bytes from memory that may not be readable to memory that may not be writable,\
+/// stopping at the first byte that cannot be copied.
+///
+/// When an exception occurs, every byte before the one that faulted has been copied, as with\
+/// `copy_from_user` in the Linux kernel.
+///
+/// # Arguments
+///
+/// * `src` - The address of the bytes to copy.
+/// * `dst` - Where the bytes are copied to.
+/// * `len` - The number of bytes to copy.
+///
+/// # Returns
+///
+/// * `Ok(())` - If every byte was copied.
+/// * `Err(PartialCopy)` - If an exception occurred, along with the number of bytes copied.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::probe;
+///
+/// let source = [1u8, 2, 3, 4];
+/// let mut buffer = [0u8; 4];
+///
+/// unsafe { probe::copy_guarded(source.as_ptr(), buffer.as_mut_ptr(), 4) }.unwrap();
+/// assert_eq!(buffer, source);
+///
+/// let partial = unsafe { probe::copy_guarded(core::ptr::null(), buffer.as_mut_ptr(), 4) };
+/// assert_eq!(partial.unwrap_err().copied(), 0);
+/// ```
+///
+/// # Safety
+///
+/// The destination, if writable, must not be in use by code that relies on its contents,\
+/// and must not overlap the source.
+pub unsafe fn copy_guarded(src: *const u8, dst: *mut u8, len: usize) -> Result<(), PartialCopy> {
+    let mut copied = 0;
+
+    try_seh(|| unsafe { copy_memory(dst.cast(), src.cast(), len, &mut copied) })
+        .map_err(|exception| PartialCopy { copied, exception })
+}
+
+/// Error of `copy_guarded`, describing how far the copy went before an exception stopped it.
+#[derive(Debug)]
+pub struct PartialCopy {
+    copied: usize,
+    exception: Exception,
+}
+
+impl PartialCopy {
+    /// # Returns
+    ///
+    /// The number of bytes at the start of the range that were copied.
+    pub fn copied(&self) -> usize {
+        self.copied
+    }
+
+    /// # Returns
+    ///
+    /// The exception that stopped the copy.
+    pub fn exception(&self) -> &Exception {
+        &self.exception
+    }
+
+    /// # Returns
+    ///
+    /// The exception that stopped the copy, consuming the error.
+    pub fn into_exception(self) -> Exception {
+        self.exception
+    }
+}
+
+impl core::fmt::Display for PartialCopy {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "copied {} bytes before {}", self.copied, self.exception)
+    }
+}
+
+/// In case the `std` feature is enabled, this implementation allows the error to be treated\
+/// as a standard error, whose source is the exception that stopped the copy.
+#[cfg(feature = "std")]
+impl std::error::Error for PartialCopy {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.exception)
+    }
+}
+
+/// # Arguments
+///
+/// * `ptr` - The start of the range.
+/// * `len` - The size of the range, in bytes.
+///
+/// # Returns
+///
+/// Whether every byte in the range can be read.
+///
+/// # Caveats
+///
+/// The result may be out of date by the time it is used, if the memory is freed or its\
+/// protection changes in the meantime.
+///
+/// On Windows, reading from a guard page consumes its guard, like any other access, which\
+/// may prevent a thread stack from growing.
+///
+/// # Safety
+///
+/// The range, if readable, must not hold memory whose accesses have side effects, such as\
+/// the registers of a device. Reading one byte of every page also commits the pages of a\
+/// lazily allocated mapping, and reads the pages of a file mapping from the disk.
+pub unsafe fn is_readable(ptr: *const u8, len: usize) -> bool {
+    do_probe(ptr, len, false)
+}
+
+/// # Arguments
+///
+/// * `ptr` - The start of the range.
+/// * `len` - The size of the range, in bytes.
+///
+/// # Returns
+///
+/// Whether every byte in the range can be written.
+///
+/// # Caveats
+///
+/// Every page in the range is tested by atomically writing one of its bytes with its own\
+/// value, so the contents of the memory never change, even if other threads write it.
+///
+/// The caveats of `is_readable` also apply.
+///
+/// # Safety
+///
+/// The range, if writable, must not hold memory whose accesses have side effects, such as\
+/// the registers of a device. Writing a byte with its own value still copies the pages of a\
+/// private mapping, and marks the pages of a shared file mapping as dirty.
+pub unsafe fn is_writable(ptr: *mut u8, len: usize) -> bool {
+    do_probe(ptr, len, true)
+}
+
+/// Reads a NUL-terminated string from memory that may not be readable.
+///
+/// # Arguments
+///
+/// * `ptr` - The address of the first character of the string.
+/// * `max` - The maximum number of bytes to read, not counting the terminator.
+///
+/// # Returns
+///
+/// * `Ok(CString)` - The string, truncated to `max` bytes if no terminator was found within them.
+/// * `Err(Exception)` - If the memory could not be read before finding the terminator.
+///
+/// # Examples
+///
+/// ```
+/// use microseh::probe;
+///
+/// let name = unsafe { probe::read_cstr(c"microseh".as_ptr(), 64) }.unwrap();
+/// assert_eq!(name.to_bytes(), b"microseh");
+///
+/// assert!(unsafe { probe::read_cstr(core::ptr::null(), 64) }.is_err());
+/// ```
+///
+/// # Safety
+///
+/// The memory, if readable, must not be written by other threads while the string is read,\
+/// and must not hold memory whose accesses have side effects, such as the registers of a device.
+#[cfg(feature = "std")]
+pub unsafe fn read_cstr(
+    ptr: *const core::ffi::c_char,
+    max: usize,
+) -> Result<std::ffi::CString, Exception> {
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; PAGE_SIZE];
+
+    try_seh(|| {
+        bytes.clear();
+
+        while bytes.len() < max {
+            // Reading up to the end of the page never faults if the first byte can be read,
+            // even if the string ends before it.
+            let current = ptr.cast::<u8>().wrapping_add(bytes.len());
+            let length = (PAGE_SIZE - current as usize % PAGE_SIZE).min(max - bytes.len());
+
+            unsafe {
+                copy_memory(
+                    chunk.as_mut_ptr().cast(),
+                    current.cast(),
+                    length,
+                    core::ptr::null_mut(),
+                )
+            };
+
+            if let Some(end) = chunk[..length].iter().position(|&byte| byte == 0) {
+                bytes.extend_from_slice(&chunk[..end]);
+                break;
+            }
+
+            bytes.extend_from_slice(&chunk[..length]);
+        }
+    })?;
+
+    // SAFETY: The bytes were read up to the first NUL character, which is not included.
+    Ok(unsafe { std::ffi::CString::from_vec_unchecked(bytes) })
+}
+
+/// Tests whether every page in the range can be accessed.
+///
+/// # Arguments
+///
+/// * `ptr` - The start of the range.
+/// * `len` - The size of the range, in bytes.
+/// * `write` - Whether the pages are tested for writing instead of reading.
+///
+/// # Returns
+///
+/// Whether every page could be accessed.
+fn do_probe(ptr: *const u8, len: usize, write: bool) -> bool {
+    // A range that wraps around the address space can never be accessed entirely.
+    if (ptr as usize).checked_add(len).is_none() {
+        return false;
+    }
+
+    try_seh(|| unsafe { probe_memory(ptr.cast::<c_void>(), len, write as i32) }).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ExceptionCode;
+
+    const INVALID_PTR: *mut u32 = core::mem::align_of::<u32>() as _;
+
+    #[test]
+    fn read_unaligned() {
+        let bytes = [0x11u8, 0x22, 0x33, 0x44, 0x55];
+        let value = unsafe { read(bytes.as_ptr().add(1).cast::<u32>()) };
+
+        assert_eq!(value.unwrap(), u32::from_ne_bytes([0x22, 0x33, 0x44, 0x55]));
+    }
+
+    #[test]
+    fn read_invalid() {
+        let ex = unsafe { read(INVALID_PTR) }.unwrap_err();
+
+        assert_eq!(ex.code(), ExceptionCode::AccessViolation);
+        assert_eq!(ex.data_address(), Some(INVALID_PTR as usize));
+    }
+
+    #[test]
+    fn write_valid_and_invalid() {
+        let mut value = 0u64;
+
+        unsafe { write(&mut value, 0x1337) }.unwrap();
+        assert_eq!(value, 0x1337);
+
+        assert!(unsafe { write(INVALID_PTR, 0) }.is_err());
+    }
+
+    #[test]
+    fn probe_ranges() {
+        let mut buffer = [0u8; PAGE_SIZE * 3];
+
+        assert!(unsafe { is_readable(buffer.as_ptr(), buffer.len()) });
+        assert!(unsafe { is_writable(buffer.as_mut_ptr(), buffer.len()) });
+        assert!(buffer.iter().all(|&byte| byte == 0));
+
+        assert!(unsafe { is_readable(core::ptr::null(), 0) });
+        assert!(!unsafe { is_readable(core::ptr::null(), 1) });
+        assert!(!unsafe { is_writable(INVALID_PTR.cast(), 4) });
+        assert!(!unsafe { is_readable(buffer.as_ptr(), usize::MAX) });
+    }
+
+    #[test]
+    fn probe_read_only() {
+        static CONSTANT: [u8; 16] = [0xAA; 16];
+
+        assert!(unsafe { is_readable(CONSTANT.as_ptr(), CONSTANT.len()) });
+        assert!(!unsafe { is_writable(CONSTANT.as_ptr().cast_mut(), CONSTANT.len()) });
+    }
+
+    /// Size of the halves of the region returned by `straddling_region`, which is a multiple
+    /// of the page size of every supported platform.
+    const HALF: usize = 0x10000;
+
+    /// Maps a region whose first half is readable and writable, and whose second half is not
+    /// accessible, calling the procedure with the start of the region.
+    #[cfg(unix)]
+    fn with_straddling_region(proc: impl FnOnce(*mut u8)) {
+        extern "C" {
+            fn mmap(
+                addr: *mut c_void,
+                len: usize,
+                prot: i32,
+                flags: i32,
+                fd: i32,
+                offset: i64,
+            ) -> *mut c_void;
+            fn mprotect(addr: *mut c_void, len: usize, prot: i32) -> i32;
+            fn munmap(addr: *mut c_void, len: usize) -> i32;
+        }
+
+        const PROT_READ_WRITE: i32 = 0x1 | 0x2;
+        const MAP_PRIVATE: i32 = 0x02;
+        #[cfg(target_os = "linux")]
+        const MAP_ANONYMOUS: i32 = 0x20;
+        #[cfg(not(target_os = "linux"))]
+        const MAP_ANONYMOUS: i32 = 0x1000;
+
+        unsafe {
+            let region = mmap(
+                core::ptr::null_mut(),
+                HALF * 2,
+                PROT_READ_WRITE,
+                MAP_PRIVATE | MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(region as isize, -1);
+            assert_eq!(mprotect(region.cast::<u8>().add(HALF).cast(), HALF, 0), 0);
+
+            proc(region.cast());
+            munmap(region, HALF * 2);
+        }
+    }
+
+    /// Maps a region whose first half is readable and writable, and whose second half is not
+    /// accessible, calling the procedure with the start of the region.
+    #[cfg(windows)]
+    fn with_straddling_region(proc: impl FnOnce(*mut u8)) {
+        extern "system" {
+            fn VirtualAlloc(addr: *mut c_void, size: usize, ty: u32, protect: u32) -> *mut c_void;
+            fn VirtualProtect(addr: *mut c_void, size: usize, protect: u32, old: *mut u32) -> i32;
+            fn VirtualFree(addr: *mut c_void, size: usize, ty: u32) -> i32;
+        }
+
+        unsafe {
+            let region = VirtualAlloc(core::ptr::null_mut(), HALF * 2, 0x3000, 0x04);
+            assert!(!region.is_null());
+
+            let mut old = 0;
+            let second = region.cast::<u8>().add(HALF).cast();
+            assert_ne!(VirtualProtect(second, HALF, 0x01, &mut old), 0);
+
+            proc(region.cast());
+            VirtualFree(region, 0, 0x8000);
+        }
+    }
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn copy_straddling_source() {
+        with_straddling_region(|region| unsafe {
+            let source = region.add(HALF - 10);
+            for i in 0..10 {
+                *source.add(i) = i as u8 + 1;
+            }
+
+            let mut buffer = [0u8; 32];
+            let partial = copy_guarded(source, buffer.as_mut_ptr(), buffer.len()).unwrap_err();
+
+            assert_eq!(partial.copied(), 10);
+            assert_eq!(&buffer[..11], &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0]);
+            assert_eq!(
+                partial.exception().data_address(),
+                Some(region as usize + HALF)
+            );
+        });
+    }
+
+    #[test]
+    fn copy_pages() {
+        let mut source = [0u8; PAGE_SIZE * 3 + 123];
+        for (i, byte) in source.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut buffer = [0u8; PAGE_SIZE * 3 + 139];
+
+        unsafe {
+            copy_guarded(
+                source[5..].as_ptr(),
+                buffer[11..].as_mut_ptr(),
+                source.len() - 5,
+            )
+        }
+        .unwrap();
+        assert_eq!(&buffer[11..source.len() + 6], &source[5..]);
+        assert!(buffer[..11].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn copy_straddling_pages() {
+        with_straddling_region(|region| unsafe {
+            region.write_bytes(0xAA, HALF);
+
+            let mut buffer = [0u8; HALF + 16];
+            let partial =
+                copy_guarded(region.add(3), buffer.as_mut_ptr().add(1), HALF).unwrap_err();
+
+            assert_eq!(partial.copied(), HALF - 3);
+            assert!(buffer[1..HALF - 2].iter().all(|&byte| byte == 0xAA));
+            assert_eq!(buffer[HALF - 2], 0);
+        });
+    }
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn copy_straddling_destination() {
+        with_straddling_region(|region| unsafe {
+            let source = [0xAAu8; 32];
+            let partial = copy_guarded(source.as_ptr(), region.add(HALF - 7), 32).unwrap_err();
+
+            assert_eq!(partial.copied(), 7);
+            assert_eq!(
+                partial.into_exception().code(),
+                ExceptionCode::AccessViolation
+            );
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn cstr_limits() {
+        let text = c"guarded parser";
+
+        unsafe {
+            assert_eq!(
+                read_cstr(text.as_ptr(), 64).unwrap().as_bytes(),
+                b"guarded parser"
+            );
+            assert_eq!(read_cstr(text.as_ptr(), 7).unwrap().as_bytes(), b"guarded");
+            assert_eq!(read_cstr(text.as_ptr(), 0).unwrap().as_bytes(), b"");
+            assert!(read_cstr(INVALID_PTR.cast(), 64).is_err());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(target_arch = "x86")]
+const NUM_REGISTERS: usize = 10;
+#[cfg(target_arch = "x86_64")]
+const NUM_REGISTERS: usize = 18;
+#[cfg(target_arch = "aarch64")]
+const NUM_REGISTERS: usize = 34;
+
+/// Names of the registers, in the same order as they appear in the register list.
+#[cfg(target_arch = "x86")]
+const NAMES: [&str; NUM_REGISTERS] = [
+    "eax", "ebx", "ecx", "edx", "esi", "edi", "ebp", "esp", "eip", "eflags",
+];
+#[cfg(target_arch = "x86_64")]
+const NAMES: [&str; NUM_REGISTERS] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "rflags",
+];
+#[cfg(target_arch = "aarch64")]
+const NAMES: [&str; NUM_REGISTERS] = [
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "x29", "x30", "sp", "pc", "cpsr",
+];
+
+/// Generates an accessor for the register stored at the given index of the register list.
+macro_rules! get_reg {
+    ($reg:ident, $index:expr) => {
+        #[doc = concat!("The value of the `", stringify!($reg), "` register.")]
+        #[inline]
+        pub fn $reg(&self) -> usize {
+            self.list[$index]
+        }
+    };
+}
+
+/// Snapshot of the general purpose registers of the thread, taken at the point where an
+/// exception occurred.
+///
+/// The available registers depend on the target architecture.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Registers {
+    list: [usize; NUM_REGISTERS],
+}
+
+impl Registers {
+    /// Creates a new register snapshot with all the registers set to zero.
+    pub(crate) fn empty() -> Self {
+        Self {
+            list: [0; NUM_REGISTERS],
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The values of all the captured registers, in architecture-specific order.
+    pub fn list(&self) -> &[usize; NUM_REGISTERS] {
+        &self.list
+    }
+
+    /// # Returns
+    ///
+    /// The values of all the captured registers, to be modified in place.
+    pub(crate) fn list_mut(&mut self) -> &mut [usize; NUM_REGISTERS] {
+        &mut self.list
+    }
+
+    /// # Returns
+    ///
+    /// An iterator over the names and values of all the captured registers.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        NAMES.iter().copied().zip(self.list.iter().copied())
+    }
+}
+
+#[cfg(target_arch = "x86")]
+impl Registers {
+    get_reg!(eax, 0);
+    get_reg!(ebx, 1);
+    get_reg!(ecx, 2);
+    get_reg!(edx, 3);
+    get_reg!(esi, 4);
+    get_reg!(edi, 5);
+    get_reg!(ebp, 6);
+    get_reg!(esp, 7);
+    get_reg!(eip, 8);
+    get_reg!(eflags, 9);
+
+    /// The value of the program counter.
+    #[inline]
+    pub fn pc(&self) -> usize {
+        self.eip()
+    }
+
+    /// The value of the stack pointer.
+    #[inline]
+    pub fn sp(&self) -> usize {
+        self.esp()
+    }
+
+    /// The value of the frame pointer.
+    #[inline]
+    pub fn fp(&self) -> usize {
+        self.ebp()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Registers {
+    get_reg!(rax, 0);
+    get_reg!(rbx, 1);
+    get_reg!(rcx, 2);
+    get_reg!(rdx, 3);
+    get_reg!(rsi, 4);
+    get_reg!(rdi, 5);
+    get_reg!(rbp, 6);
+    get_reg!(rsp, 7);
+    get_reg!(r8, 8);
+    get_reg!(r9, 9);
+    get_reg!(r10, 10);
+    get_reg!(r11, 11);
+    get_reg!(r12, 12);
+    get_reg!(r13, 13);
+    get_reg!(r14, 14);
+    get_reg!(r15, 15);
+    get_reg!(rip, 16);
+    get_reg!(rflags, 17);
+
+    /// The value of the program counter.
+    #[inline]
+    pub fn pc(&self) -> usize {
+        self.rip()
+    }
+
+    /// The value of the stack pointer.
+    #[inline]
+    pub fn sp(&self) -> usize {
+        self.rsp()
+    }
+
+    /// The value of the frame pointer.
+    #[inline]
+    pub fn fp(&self) -> usize {
+        self.rbp()
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl Registers {
+    get_reg!(x0, 0);
+    get_reg!(x1, 1);
+    get_reg!(x2, 2);
+    get_reg!(x3, 3);
+    get_reg!(x4, 4);
+    get_reg!(x5, 5);
+    get_reg!(x6, 6);
+    get_reg!(x7, 7);
+    get_reg!(x8, 8);
+    get_reg!(x9, 9);
+    get_reg!(x10, 10);
+    get_reg!(x11, 11);
+    get_reg!(x12, 12);
+    get_reg!(x13, 13);
+    get_reg!(x14, 14);
+    get_reg!(x15, 15);
+    get_reg!(x16, 16);
+    get_reg!(x17, 17);
+    get_reg!(x18, 18);
+    get_reg!(x19, 19);
+    get_reg!(x20, 20);
+    get_reg!(x21, 21);
+    get_reg!(x22, 22);
+    get_reg!(x23, 23);
+    get_reg!(x24, 24);
+    get_reg!(x25, 25);
+    get_reg!(x26, 26);
+    get_reg!(x27, 27);
+    get_reg!(x28, 28);
+    get_reg!(x29, 29);
+    get_reg!(x30, 30);
+    get_reg!(lr, 30);
+    get_reg!(cpsr, 33);
+
+    /// The value of the program counter.
+    #[inline]
+    pub fn pc(&self) -> usize {
+        self.list[32]
+    }
+
+    /// The value of the stack pointer.
+    #[inline]
+    pub fn sp(&self) -> usize {
+        self.list[31]
+    }
+
+    /// The value of the frame pointer (`x29`).
+    #[inline]
+    pub fn fp(&self) -> usize {
+        self.list[29]
+    }
+}
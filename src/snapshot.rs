@@ -0,0 +1,77 @@
+/// Size of the window captured at the address of the faulting instruction.
+pub(crate) const CODE_SNAPSHOT_SIZE: usize = 32;
+/// Size of the window captured from the stack pointer upwards.
+pub(crate) const STACK_SNAPSHOT_SIZE: usize = 256;
+/// Size of the window captured around the address of the data that could not be accessed.
+pub(crate) const DATA_SNAPSHOT_SIZE: usize = 64;
+
+/// Mirrors the `MS_SNAPSHOT` structure of the C stub, which holds the bytes read from an address.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct RawSnapshot<const N: usize> {
+    address: usize,
+    length: u32,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> RawSnapshot<N> {
+    /// Creates a new snapshot that holds no bytes.
+    pub(crate) const fn empty() -> Self {
+        Self {
+            address: 0,
+            length: 0,
+            bytes: [0; N],
+        }
+    }
+
+    /// # Returns
+    ///
+    /// * `Some(Snapshot)` - The bytes that could be read.
+    /// * `None` - If no bytes could be read.
+    pub(crate) fn get(&self) -> Option<Snapshot<'_>> {
+        let length = (self.length as usize).min(N);
+        if length == 0 {
+            return None;
+        }
+
+        Some(Snapshot {
+            address: self.address,
+            bytes: &self.bytes[..length],
+        })
+    }
+}
+
+/// Copy of a small region of memory, taken at the point where an exception occurred.
+///
+/// The region is read defensively, stopping at the first page that cannot be read, so the
+/// snapshot may be shorter than the window that was requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Snapshot<'a> {
+    address: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> Snapshot<'a> {
+    /// # Returns
+    ///
+    /// The address the first byte was read from.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// # Returns
+    ///
+    /// The bytes that were read, starting at `address`.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// # Returns
+    ///
+    /// Whether the byte at the given address is part of the snapshot.
+    pub fn contains(&self, address: usize) -> bool {
+        address
+            .checked_sub(self.address)
+            .is_some_and(|offset| offset < self.bytes.len())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::Exception;
+
+/// Result of a procedure executed by `try_seh_on_stack`, along with how much of its dedicated\
+/// stack the procedure used.
+#[derive(Debug)]
+pub struct StackRun<R> {
+    result: Result<R, Exception>,
+    high_water_mark: usize,
+}
+
+impl<R> StackRun<R> {
+    pub(crate) fn new(result: Result<R, Exception>, high_water_mark: usize) -> Self {
+        Self {
+            result,
+            high_water_mark,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Whether the procedure executed without throwing any exceptions.
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// # Returns
+    ///
+    /// Whether an exception occurred during the execution of the procedure.
+    pub fn is_err(&self) -> bool {
+        self.result.is_err()
+    }
+
+    /// # Returns
+    ///
+    /// The deepest extent of the stack that was reached by the procedure, in bytes. It is\
+    /// rounded up to whole pages, and is zero if the platform cannot measure it.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    /// # Returns
+    ///
+    /// * `Ok(R)` - If the procedure executed without throwing any exceptions.
+    /// * `Err(Exception)` - If an exception occurred during the execution of the procedure.
+    pub fn into_result(self) -> Result<R, Exception> {
+        self.result
+    }
+}
+
+impl<R> From<StackRun<R>> for Result<R, Exception> {
+    fn from(run: StackRun<R>) -> Self {
+        run.into_result()
+    }
+}
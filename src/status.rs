@@ -0,0 +1,298 @@
+use crate::code::ExceptionCode;
+
+/// Bit that marks an HRESULT as a wrapped NTSTATUS value.
+const FACILITY_NT_BIT: u32 = 0x1000_0000;
+
+/// Bit that marks a value as defined by a third party rather than by Microsoft.
+const CUSTOMER_BIT: u32 = 0x2000_0000;
+
+/// Facility used by NTSTATUS values that wrap Win32 error codes.
+const FACILITY_NTWIN32: u16 = 0x7;
+
+/// Facility used by HRESULT values that wrap Win32 error codes.
+const FACILITY_WIN32: u16 = 0x7;
+
+/// Known NTSTATUS values that do not embed their Win32 equivalent, along with the error code
+/// `RtlNtStatusToDosError` translates them to.
+const WIN32_MAPPINGS: &[(u32, u32)] = &[
+    (0x0000_0000, 0),    // STATUS_SUCCESS -> ERROR_SUCCESS
+    (0x0000_0102, 258),  // STATUS_TIMEOUT -> WAIT_TIMEOUT
+    (0x0000_0103, 997),  // STATUS_PENDING -> ERROR_IO_PENDING
+    (0x8000_0002, 998),  // STATUS_DATATYPE_MISALIGNMENT -> ERROR_NOACCESS
+    (0x8000_0005, 234),  // STATUS_BUFFER_OVERFLOW -> ERROR_MORE_DATA
+    (0x8000_0006, 18),   // STATUS_NO_MORE_FILES -> ERROR_NO_MORE_FILES
+    (0xC000_0002, 1),    // STATUS_NOT_IMPLEMENTED -> ERROR_INVALID_FUNCTION
+    (0xC000_0005, 998),  // STATUS_ACCESS_VIOLATION -> ERROR_NOACCESS
+    (0xC000_0006, 999),  // STATUS_IN_PAGE_ERROR -> ERROR_SWAPERROR
+    (0xC000_0008, 6),    // STATUS_INVALID_HANDLE -> ERROR_INVALID_HANDLE
+    (0xC000_000D, 87),   // STATUS_INVALID_PARAMETER -> ERROR_INVALID_PARAMETER
+    (0xC000_000F, 2),    // STATUS_NO_SUCH_FILE -> ERROR_FILE_NOT_FOUND
+    (0xC000_0010, 1),    // STATUS_INVALID_DEVICE_REQUEST -> ERROR_INVALID_FUNCTION
+    (0xC000_0011, 38),   // STATUS_END_OF_FILE -> ERROR_HANDLE_EOF
+    (0xC000_0017, 8),    // STATUS_NO_MEMORY -> ERROR_NOT_ENOUGH_MEMORY
+    (0xC000_0022, 5),    // STATUS_ACCESS_DENIED -> ERROR_ACCESS_DENIED
+    (0xC000_0023, 122),  // STATUS_BUFFER_TOO_SMALL -> ERROR_INSUFFICIENT_BUFFER
+    (0xC000_0034, 2),    // STATUS_OBJECT_NAME_NOT_FOUND -> ERROR_FILE_NOT_FOUND
+    (0xC000_0035, 183),  // STATUS_OBJECT_NAME_COLLISION -> ERROR_ALREADY_EXISTS
+    (0xC000_003A, 3),    // STATUS_OBJECT_PATH_NOT_FOUND -> ERROR_PATH_NOT_FOUND
+    (0xC000_0043, 32),   // STATUS_SHARING_VIOLATION -> ERROR_SHARING_VIOLATION
+    (0xC000_007F, 112),  // STATUS_DISK_FULL -> ERROR_DISK_FULL
+    (0xC000_009A, 1450), // STATUS_INSUFFICIENT_RESOURCES -> ERROR_NO_SYSTEM_RESOURCES
+    (0xC000_00BB, 50),   // STATUS_NOT_SUPPORTED -> ERROR_NOT_SUPPORTED
+    (0xC000_00FD, 1001), // STATUS_STACK_OVERFLOW -> ERROR_STACK_OVERFLOW
+    (0xC000_0120, 995),  // STATUS_CANCELLED -> ERROR_OPERATION_ABORTED
+];
+
+/// Represents the severity of an NTSTATUS value, stored in its two most significant bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Success = 0x0,
+    Informational = 0x1,
+    Warning = 0x2,
+    Error = 0x3,
+}
+
+/// Represents a 32-bit NTSTATUS value, decoded into its individual fields.
+///
+/// Every exception code is an NTSTATUS value, so this type can be used to inspect codes that
+/// are not known to `ExceptionCode`, as well as to normalize HRESULTs and Win32 error codes.
+///
+/// See: <https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-erref/87fba13e-bf06-450e-83b1-9241dc81e781>
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NtStatus(u32);
+
+impl NtStatus {
+    /// Creates a new status from its raw 32-bit value.
+    pub const fn new(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    /// Creates a new status from its individual fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `severity` - The severity of the status.
+    /// * `customer` - Whether the status is defined by a third party.
+    /// * `facility` - The facility that defines the status, truncated to 12 bits.
+    /// * `code` - The facility-specific code of the status.
+    pub const fn from_parts(severity: Severity, customer: bool, facility: u16, code: u16) -> Self {
+        let customer = if customer { CUSTOMER_BIT } else { 0 };
+        Self(
+            ((severity as u32) << 30)
+                | customer
+                | (((facility & 0xFFF) as u32) << 16)
+                | code as u32,
+        )
+    }
+
+    /// Creates a new status that wraps a Win32 error code, like `NTSTATUS_FROM_WIN32` does.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The Win32 error code to wrap.
+    ///
+    /// # Caveats
+    ///
+    /// Like `NTSTATUS_FROM_WIN32`, the wrapped status has warning severity (`0x8007xxxx`), \
+    /// and values that are zero or negative when read as an `NTSTATUS` are passed through unchanged.
+    pub const fn from_win32(error: u32) -> Self {
+        if error as i32 <= 0 {
+            Self(error)
+        } else {
+            Self::from_parts(Severity::Warning, false, FACILITY_NTWIN32, error as u16)
+        }
+    }
+
+    /// Converts an HRESULT back into the status it was created from.
+    ///
+    /// # Arguments
+    ///
+    /// * `hresult` - The HRESULT to convert.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(NtStatus)` - If the HRESULT wraps an NTSTATUS or a Win32 error code.
+    /// * `None` - If there is no equivalent status for the HRESULT.
+    pub const fn from_hresult(hresult: u32) -> Option<Self> {
+        if hresult == 0 {
+            Some(Self(0))
+        } else if hresult & FACILITY_NT_BIT != 0 {
+            Some(Self(hresult & !FACILITY_NT_BIT))
+        } else if hresult >> 31 == 1 && ((hresult >> 16) & 0xFFF) as u16 == FACILITY_WIN32 {
+            Some(Self::from_win32(hresult & 0xFFFF))
+        } else {
+            None
+        }
+    }
+
+    /// # Returns
+    ///
+    /// The raw 32-bit value of the status.
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    /// # Returns
+    ///
+    /// The severity of the status.
+    pub const fn severity(self) -> Severity {
+        match self.0 >> 30 {
+            0x0 => Severity::Success,
+            0x1 => Severity::Informational,
+            0x2 => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Whether the status is defined by a third party rather than by Microsoft.
+    pub const fn is_customer(self) -> bool {
+        self.0 & CUSTOMER_BIT != 0
+    }
+
+    /// # Returns
+    ///
+    /// The facility that defines the status.
+    pub const fn facility(self) -> u16 {
+        ((self.0 >> 16) & 0xFFF) as u16
+    }
+
+    /// # Returns
+    ///
+    /// The facility-specific code of the status.
+    pub const fn code(self) -> u16 {
+        self.0 as u16
+    }
+
+    /// # Returns
+    ///
+    /// Whether the status represents a success or an informational value, like `NT_SUCCESS`.
+    pub const fn is_success(self) -> bool {
+        matches!(self.severity(), Severity::Success | Severity::Informational)
+    }
+
+    /// # Returns
+    ///
+    /// Whether the status represents an error.
+    pub const fn is_error(self) -> bool {
+        matches!(self.severity(), Severity::Error)
+    }
+
+    /// # Returns
+    ///
+    /// The HRESULT that wraps this status, like `HRESULT_FROM_NT` does.
+    pub const fn to_hresult(self) -> u32 {
+        self.0 | FACILITY_NT_BIT
+    }
+
+    /// # Returns
+    ///
+    /// * `Some(u32)` - The equivalent Win32 error code, if a mapping for the status is known.
+    /// * `None` - If the status has no known Win32 equivalent.
+    pub fn to_win32(self) -> Option<u32> {
+        if matches!(self.severity(), Severity::Warning)
+            && !self.is_customer()
+            && self.facility() == FACILITY_NTWIN32
+        {
+            return Some(self.code() as u32);
+        }
+
+        WIN32_MAPPINGS
+            .iter()
+            .find(|(status, _)| *status == self.0)
+            .map(|(_, error)| *error)
+    }
+}
+
+impl From<u32> for NtStatus {
+    fn from(raw: u32) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<NtStatus> for u32 {
+    fn from(status: NtStatus) -> Self {
+        status.0
+    }
+}
+
+impl From<ExceptionCode> for NtStatus {
+    fn from(code: ExceptionCode) -> Self {
+        Self(code.raw())
+    }
+}
+
+impl From<NtStatus> for ExceptionCode {
+    fn from(status: NtStatus) -> Self {
+        ExceptionCode::from(status.0)
+    }
+}
+
+impl core::fmt::Display for NtStatus {
+    /// Formats the status as a hexadecimal value, which is how they are usually documented.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The formatter to write to.
+    ///
+    /// # Returns
+    ///
+    /// Whether the formatting operation succeeded.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x{:08X}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let status = NtStatus::new(0xC0000005);
+        assert_eq!(status.severity(), Severity::Error);
+        assert!(!status.is_customer());
+        assert_eq!(status.facility(), 0x0);
+        assert_eq!(status.code(), 0x5);
+        assert!(status.is_error());
+
+        let status = NtStatus::new(0xE06D7363);
+        assert!(status.is_customer());
+        assert_eq!(status.facility(), 0x6D);
+        assert_eq!(status.code(), 0x7363);
+
+        assert_eq!(
+            NtStatus::from_parts(Severity::Informational, true, 0x6D, 0x1388),
+            NtStatus::new(0x606D1388)
+        );
+        assert!(NtStatus::new(0x40010006).is_success());
+    }
+
+    #[test]
+    fn hresult() {
+        let status = NtStatus::new(0xC0000005);
+        assert_eq!(status.to_hresult(), 0xD0000005);
+        assert_eq!(NtStatus::from_hresult(0xD0000005), Some(status));
+
+        // E_ACCESSDENIED wraps ERROR_ACCESS_DENIED.
+        assert_eq!(
+            NtStatus::from_hresult(0x80070005),
+            Some(NtStatus::new(0x80070005))
+        );
+        assert_eq!(NtStatus::from_hresult(0), Some(NtStatus::new(0)));
+        assert_eq!(NtStatus::from_hresult(0x80004005), None);
+    }
+
+    #[test]
+    fn win32() {
+        assert_eq!(NtStatus::new(0xC0000005).to_win32(), Some(998));
+        assert_eq!(NtStatus::from_win32(1450).to_win32(), Some(1450));
+        assert_eq!(NtStatus::from_win32(0), NtStatus::new(0));
+        assert_eq!(NtStatus::from_win32(5), NtStatus::new(0x80070005));
+        assert_eq!(NtStatus::new(0x80070005).to_win32(), Some(5));
+        assert_eq!(NtStatus::from_win32(0xC0000005), NtStatus::new(0xC0000005));
+        assert_eq!(NtStatus::new(0xC000001D).to_win32(), None);
+    }
+}
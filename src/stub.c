@@ -1,63 +1,165 @@
-#include <stdint.h>
-
-#define MS_SUCCEEDED 0x0
-#define MS_CATCHED 0x1
-
-#define TG_ARCH_X86 1
-#define TG_ARCH_X64 2
-#define TG_ARCH_ARM64 3
-#define TG_ARCH_UNKNOWN 4
-
-#if defined(_M_IX86)
-#define TG_ARCH TG_ARCH_X86
-#elif defined(_M_X64) || defined(_M_AMD64)
-#define TG_ARCH TG_ARCH_X64
-#elif defined(_M_ARM64)
-#define TG_ARCH TG_ARCH_ARM64
-#else
-#define TG_ARCH TG_ARCH_UNKNOWN
-#endif
+#include <string.h>
+
+#include "stub.h"
+
+// This file does not include any header of the Windows SDK, so that it can be linked in kernel
+// drivers. The parts of the backend that need the user-mode libraries are found in stub_user.c.
 
 #define EXCEPTION_EXECUTE_HANDLER      1
+#define EXCEPTION_MAXIMUM_PARAMETERS   15
+#define EXCEPTION_STACK_OVERFLOW       0xC00000FD
 #define GetExceptionCode            _exception_code
-#if defined(_MSC_VER)
-    #define TG_CDECL __cdecl
-    #define TG_STDCALL __stdcall
-#else
-    #define TG_CDECL __attribute__((cdecl))
-    #define TG_STDCALL __attribute__((stdcall))
-#endif
+#define GetExceptionInformation()   ((PEXCEPTION_POINTERS)_exception_info())
 
 unsigned long TG_CDECL _exception_code(void);
+void* TG_CDECL _exception_info(void);
+
+// Same layout as the EXCEPTION_RECORD structure of the system.
+typedef struct _EXCEPTION_RECORD
+{
+    uint32_t ExceptionCode;
+    uint32_t ExceptionFlags;
+    struct _EXCEPTION_RECORD* ExceptionRecord;
+    void* ExceptionAddress;
+    uint32_t NumberParameters;
+    uintptr_t ExceptionInformation[EXCEPTION_MAXIMUM_PARAMETERS];
+} EXCEPTION_RECORD, *PEXCEPTION_RECORD;
 
-typedef void (TG_STDCALL *PPROC_EXECUTOR)(void* Proc);
+// Same layout as the EXCEPTION_POINTERS structure of the system. The CONTEXT is only read by
+// the user-mode part of the backend.
+typedef struct _EXCEPTION_POINTERS
+{
+    PEXCEPTION_RECORD ExceptionRecord;
+    void* ContextRecord;
+} EXCEPTION_POINTERS, *PEXCEPTION_POINTERS;
 
-typedef struct _EXCEPTION
+static void CopyRecord(PEXCEPTION_RECORD Source, PRECORD Destination)
 {
-    uint32_t Code;
-} EXCEPTION, *PEXCEPTION;
+    uint32_t Count = Source->NumberParameters;
+    if (Count > MS_MAXIMUM_PARAMETERS)
+    {
+        Count = MS_MAXIMUM_PARAMETERS;
+    }
+
+    Destination->Code = Source->ExceptionCode;
+    Destination->Flags = Source->ExceptionFlags;
+    Destination->Address = (uintptr_t)Source->ExceptionAddress;
+    Destination->NumberParameters = Count;
+    for (uint32_t i = 0; i < Count; ++i)
+    {
+        Destination->Information[i] = Source->ExceptionInformation[i];
+    }
+}
+
+static int HandlerFilter(
+    uint32_t Code,
+    PEXCEPTION_POINTERS Pointers,
+    PFILTER_EXECUTOR FilterExecutor,
+    void* Filter,
+    PEXCEPTION Exception,
+    uint32_t Flags
+) {
+    if (Exception != NULL)
+    {
+        // The same exception may be filled more than once, if execution was continued.
+        memset(Exception, 0, sizeof(*Exception));
+
+        if (Pointers != NULL && Pointers->ExceptionRecord != NULL)
+        {
+            PEXCEPTION_RECORD Nested = Pointers->ExceptionRecord->ExceptionRecord;
+
+            CopyRecord(Pointers->ExceptionRecord, &Exception->Record);
+
+            // Exceptions raised while another one was being dispatched are linked to it.
+            while (Nested != NULL && Exception->NestedCount < MS_MAXIMUM_NESTED)
+            {
+                CopyRecord(Nested, &Exception->Nested[Exception->NestedCount++]);
+                Nested = Nested->ExceptionRecord;
+            }
+        }
+
+        // Use GetExceptionCode() instead of Record->ExceptionCode as it is more reliable.
+        Exception->Record.Code = Code;
+
+#if TG_ARCH != TG_ARCH_UNKNOWN && defined(MS_USER_MODE)
+        if (Pointers != NULL && Pointers->ContextRecord != NULL)
+        {
+            __microseh_CaptureContext(Pointers->ContextRecord, Exception);
+        }
+#endif
+
+#if defined(MS_USER_MODE)
+        if ((Flags & MS_CAPTURE_MEMORY) != 0)
+        {
+            __microseh_CaptureMemory(Exception);
+        }
+#else
+        (void)Flags;
+#endif
+
+        if (FilterExecutor != NULL)
+        {
+            int Disposition = FilterExecutor(Filter, Exception);
+
+#if TG_ARCH != TG_ARCH_UNKNOWN && defined(MS_USER_MODE)
+            // The filter may have changed the registers the execution continues with.
+            if (Disposition == MS_CONTINUE_EXECUTION && Pointers != NULL && Pointers->ContextRecord != NULL)
+            {
+                __microseh_ApplyContext(Pointers->ContextRecord, Exception);
+            }
+#endif
+
+            return Disposition;
+        }
+    }
+
+    return EXCEPTION_EXECUTE_HANDLER;
+}
 
 uint32_t __microseh_HandlerStub(
     PPROC_EXECUTOR ProcExecutor,
     void* Proc,
-    PEXCEPTION Exception
+    PFILTER_EXECUTOR FilterExecutor,
+    void* Filter,
+    PEXCEPTION Exception,
+    uint32_t Flags
 ) {
     uint32_t Result = MS_SUCCEEDED;
-    uint32_t Code = 0;
+    volatile uint32_t Code = 0;
 
     __try
     {
         ProcExecutor(Proc);
     }
-    __except (Code = GetExceptionCode(), EXCEPTION_EXECUTE_HANDLER)
+    __except (HandlerFilter(Code = GetExceptionCode(), GetExceptionInformation(), FilterExecutor, Filter, Exception, Flags))
     {
         Result = MS_CATCHED;
-        if (Exception != NULL)
+
+#if defined(MS_USER_MODE)
+        // The guard page of the stack was consumed by the overflow, and can only be restored now
+        // that the stack has been unwound, so that the next overflow raises the exception again.
+        if (Code == EXCEPTION_STACK_OVERFLOW)
         {
-            // Use GetExceptionCode() instead of Record->ExceptionCode as it is more reliable.
-            Exception->Code = Code;
+            __microseh_ResetStackGuard();
         }
+#endif
     }
 
     return Result;
 }
+
+void __microseh_FinallyStub(
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PPROC_EXECUTOR FinalizerExecutor,
+    void* Finalizer
+) {
+    __try
+    {
+        ProcExecutor(Proc);
+    }
+    __finally
+    {
+        FinalizerExecutor(Finalizer);
+    }
+}
@@ -0,0 +1,226 @@
+#ifndef MICROSEH_STUB_H
+#define MICROSEH_STUB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define MS_SUCCEEDED 0x0
+#define MS_CATCHED 0x1
+#define MS_STACK_UNAVAILABLE 0x2
+
+#define TG_ARCH_X86 1
+#define TG_ARCH_X64 2
+#define TG_ARCH_ARM64 3
+#define TG_ARCH_UNKNOWN 4
+
+#if defined(_M_IX86) || defined(__i386__)
+#define TG_ARCH TG_ARCH_X86
+#elif defined(_M_X64) || defined(_M_AMD64) || defined(__x86_64__)
+#define TG_ARCH TG_ARCH_X64
+#elif defined(_M_ARM64) || defined(__aarch64__)
+#define TG_ARCH TG_ARCH_ARM64
+#else
+#define TG_ARCH TG_ARCH_UNKNOWN
+#endif
+
+// Number of general purpose registers captured for each architecture. Must match the lists
+// in `registers.rs`, as the order of the registers is shared by both sides.
+#if TG_ARCH == TG_ARCH_X86
+#define MS_NUM_REGISTERS 10
+#define MS_REG_SP 7
+#define MS_REG_PC 8
+#elif TG_ARCH == TG_ARCH_X64
+#define MS_NUM_REGISTERS 18
+#define MS_REG_SP 7
+#define MS_REG_PC 16
+#elif TG_ARCH == TG_ARCH_ARM64
+#define MS_NUM_REGISTERS 34
+#define MS_REG_SP 31
+#define MS_REG_PC 32
+#endif
+
+#if defined(_MSC_VER)
+    #define TG_CDECL __cdecl
+    #define TG_STDCALL __stdcall
+#elif defined(_WIN32)
+    #define TG_CDECL __attribute__((cdecl))
+    #define TG_STDCALL __attribute__((stdcall))
+#else
+    // Outside of Windows, `extern "system"` is the same as `extern "C"`.
+    #define TG_CDECL
+    #define TG_STDCALL
+#endif
+
+typedef void (TG_STDCALL *PPROC_EXECUTOR)(void* Proc);
+
+// Maximum number of parameters that can be attached to an exception, as EXCEPTION_MAXIMUM_PARAMETERS.
+#define MS_MAXIMUM_PARAMETERS 15
+
+// Values of the first parameter of memory faults, describing the kind of access that failed.
+#define MS_ACCESS_READ 0
+#define MS_ACCESS_WRITE 1
+#define MS_ACCESS_EXECUTE 8
+
+// Fields of the siginfo_t structure that describe a signal. Only filled on POSIX platforms.
+typedef struct _SIGNAL_INFO
+{
+    int32_t Signal;
+    int32_t Code;
+    int32_t Errno;
+    uintptr_t Address;
+} SIGNAL_INFO, *PSIGNAL_INFO;
+
+// Maximum number of nested exception records that are captured along with an exception.
+#define MS_MAXIMUM_NESTED 4
+
+// Data of an EXCEPTION_RECORD, without the link to the nested record.
+typedef struct _RECORD
+{
+    uint32_t Code;
+    uint32_t Flags;
+    uint32_t NumberParameters;
+    uintptr_t Address;
+    uintptr_t Information[MS_MAXIMUM_PARAMETERS];
+} RECORD, *PRECORD;
+
+// Parts of the floating-point and vector state that were captured, stored in FPU_STATE.Features.
+#define MS_FPU_X87 0x1
+#define MS_FPU_SSE 0x2
+#define MS_FPU_AVX 0x4
+#define MS_FPU_AVX512 0x8
+#define MS_FPU_NEON 0x1
+
+// Floating-point and vector registers. Must match the layout of `FpuState` in `fpu.rs`.
+#if TG_ARCH == TG_ARCH_X86 || TG_ARCH == TG_ARCH_X64
+typedef struct _FPU_STATE
+{
+    uint32_t Features;
+    uint16_t ControlWord;
+    uint16_t StatusWord;
+    uint16_t TagWord;
+    uint16_t Reserved;
+    uint32_t Mxcsr;
+    // x87 registers, in the 80-bit format, padded to 128 bits.
+    uint64_t St[8][2];
+    // Full width of each vector register, whose lower parts are the XMM and YMM registers.
+    uint64_t Zmm[32][8];
+    uint64_t Opmask[8];
+} FPU_STATE, *PFPU_STATE;
+#elif TG_ARCH == TG_ARCH_ARM64
+typedef struct _FPU_STATE
+{
+    uint32_t Features;
+    uint32_t Fpcr;
+    uint32_t Fpsr;
+    uint64_t V[32][2];
+} FPU_STATE, *PFPU_STATE;
+#endif
+
+// Sizes of the memory windows that are captured around the point where an exception occurred.
+#define MS_CODE_SNAPSHOT_SIZE 32
+#define MS_STACK_SNAPSHOT_SIZE 256
+#define MS_DATA_SNAPSHOT_SIZE 64
+
+// Bytes read from the given address, of which only the first Length bytes could be read.
+#define MS_SNAPSHOT(Size) struct { uintptr_t Address; uint32_t Length; uint8_t Bytes[Size]; }
+
+typedef struct _EXCEPTION
+{
+    RECORD Record;
+    SIGNAL_INFO SignalInfo;
+#if TG_ARCH != TG_ARCH_UNKNOWN
+    uintptr_t Registers[MS_NUM_REGISTERS];
+    FPU_STATE Fpu;
+#endif
+    uint32_t NestedCount;
+    RECORD Nested[MS_MAXIMUM_NESTED];
+    MS_SNAPSHOT(MS_CODE_SNAPSHOT_SIZE) Code;
+    MS_SNAPSHOT(MS_STACK_SNAPSHOT_SIZE) Stack;
+    MS_SNAPSHOT(MS_DATA_SNAPSHOT_SIZE) Data;
+} EXCEPTION, *PEXCEPTION;
+
+// Values returned by a filter, as the ones of the filter expression of an __except block.
+#define MS_EXECUTE_HANDLER 1
+#define MS_CONTINUE_SEARCH 0
+#define MS_CONTINUE_EXECUTION -1
+
+typedef int32_t (TG_STDCALL *PFILTER_EXECUTOR)(void* Filter, PEXCEPTION Exception);
+
+// Flags of the handler stub, which select what is captured along with an exception.
+#define MS_CAPTURE_MEMORY 0x1
+
+// Executes the procedure, catching the exceptions for which the filter returns
+// MS_EXECUTE_HANDLER. Every exception is caught if the filter executor is NULL. The memory
+// around the exception is only read into the snapshots if Flags has MS_CAPTURE_MEMORY.
+uint32_t __microseh_HandlerStub(
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PFILTER_EXECUTOR FilterExecutor,
+    void* Filter,
+    PEXCEPTION Exception,
+    uint32_t Flags
+);
+
+// Executes the procedure, then the finalizer. The finalizer also runs when an exception raised
+// by the procedure is handled by an outer region, before the execution leaves the procedure.
+void __microseh_FinallyStub(
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PPROC_EXECUTOR FinalizerExecutor,
+    void* Finalizer
+);
+
+// Raises a software exception with the given record, returning only if the execution is
+// continued by a filter. Only built for user mode on Windows, as it calls RaiseException.
+void __microseh_RaiseException(const RECORD* Record);
+
+// Raises the captured exception again with the same record, as if it had never been caught.
+// Never returns, as a NONCONTINUABLE_EXCEPTION is raised if a filter continues the execution.
+// Only built for user mode on Windows, as it raises the exception through ntdll.
+void __microseh_RethrowException(const EXCEPTION* Exception);
+
+// Executes the procedure like the handler stub without a filter, on a new stack of at least the
+// given size that is freed afterwards. Stores how much of the stack was used, rounded up to
+// whole pages, and returns MS_STACK_UNAVAILABLE if the stack could not be created. Only built
+// for user mode on Windows, as it runs the procedure on a fiber.
+uint32_t __microseh_StackStub(
+    size_t Size,
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PEXCEPTION Exception,
+    size_t* HighWaterMark
+);
+
+#if defined(MS_USER_MODE)
+// Parts of the Windows backend that are only built for user mode, as they call into the Win32 API
+// or the C runtime. The handler stub only calls them when MS_USER_MODE is defined.
+
+// Restores the guard page of the stack after a stack overflow was handled.
+void __microseh_ResetStackGuard(void);
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+// Copies the general purpose, floating-point and vector registers from the CONTEXT of the
+// exception, including the extended state found with LocateXStateFeature.
+void __microseh_CaptureContext(void* Context, PEXCEPTION Exception);
+
+// Writes the general purpose registers of the exception back to its CONTEXT, the opposite of
+// __microseh_CaptureContext.
+void __microseh_ApplyContext(void* Context, const EXCEPTION* Exception);
+#endif
+
+// Reads the memory around the point where the exception occurred into its snapshots, skipping
+// the pages that VirtualQuery reports as inaccessible.
+void __microseh_CaptureMemory(PEXCEPTION Exception);
+#endif
+
+// Copies the memory in chunks that do not cross a page of either range, accessing the first byte of
+// each chunk on its own, so that a fault only occurs on a byte that follows every copied one. The
+// number of bytes copied so far is stored after each access, unless Copied is NULL, for when a
+// fault stops the copy. The ranges must not overlap.
+void __microseh_CopyMemory(void* Destination, const void* Source, size_t Size, volatile size_t* Copied);
+
+// Accesses a byte of every page in the range, faulting if one cannot be read, or written if Write
+// is not zero. Bytes are written with their own value, atomically, so their contents never change.
+void __microseh_ProbeMemory(const void* Address, size_t Size, int32_t Write);
+
+#endif // MICROSEH_STUB_H
@@ -0,0 +1,1255 @@
+#define _GNU_SOURCE
+
+#include <pthread.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <setjmp.h>
+#include <signal.h>
+#include <stddef.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+#if defined(__linux__)
+#include <sys/uio.h>
+#endif
+
+#if (defined(__i386__) || defined(__x86_64__)) && defined(__linux__)
+#include <cpuid.h>
+#endif
+
+#if defined(__APPLE__)
+#include <sys/ucontext.h>
+#else
+#include <ucontext.h>
+#endif
+
+#include "stub.h"
+
+// Windows exception codes that the POSIX signals are translated to, so that the Rust side
+// can treat both backends the same way.
+#define STATUS_ACCESS_VIOLATION          0xC0000005
+#define STATUS_IN_PAGE_ERROR             0xC0000006
+#define STATUS_ILLEGAL_INSTRUCTION       0xC000001D
+#define STATUS_ARRAY_BOUNDS_EXCEEDED     0xC000008C
+#define STATUS_FLOAT_DIVIDE_BY_ZERO      0xC000008E
+#define STATUS_FLOAT_INEXACT_RESULT      0xC000008F
+#define STATUS_FLOAT_INVALID_OPERATION   0xC0000090
+#define STATUS_FLOAT_OVERFLOW            0xC0000091
+#define STATUS_FLOAT_UNDERFLOW           0xC0000093
+#define STATUS_INTEGER_DIVIDE_BY_ZERO    0xC0000094
+#define STATUS_INTEGER_OVERFLOW          0xC0000095
+#define STATUS_PRIVILEGED_INSTRUCTION    0xC0000096
+#define STATUS_STACK_OVERFLOW            0xC00000FD
+#define STATUS_DATATYPE_MISALIGNMENT     0x80000002
+#define STATUS_BREAKPOINT                0x80000003
+#define STATUS_SINGLE_STEP               0x80000004
+#define STATUS_NONCONTINUABLE_EXCEPTION  0xC0000025
+
+// Flags of the exception records, as the EXCEPTION_* flags of Windows.
+#define EXCEPTION_NONCONTINUABLE         0x1
+#define EXCEPTION_SOFTWARE_ORIGINATE     0x80
+
+// Guarded region, which either handles exceptions, or runs a finalizer when it is left by
+// jumping to an outer region, if FinalizerExecutor is not NULL.
+typedef struct _FRAME
+{
+    sigjmp_buf Env;
+    PFILTER_EXECUTOR FilterExecutor;
+    void* Filter;
+    PEXCEPTION Exception;
+    uint32_t Flags;
+    PPROC_EXECUTOR FinalizerExecutor;
+    void* Finalizer;
+    struct _FRAME* Previous;
+} FRAME, *PFRAME;
+
+#if defined(SIGRTMIN)
+// Software exceptions are raised with a signal of their own, as debuggers stop on SIGTRAP and
+// discard it, which would raise a noncontinuable exception forever. Real-time signals are only
+// numbered at runtime, so the last handled signal is filled in when the handlers are installed.
+#define SOFTWARE_SIGNAL (SIGRTMIN + 3)
+
+static int HandledSignals[] = { SIGSEGV, SIGBUS, SIGFPE, SIGILL, SIGTRAP, 0 };
+#else
+#define SOFTWARE_SIGNAL SIGTRAP
+
+static int HandledSignals[] = { SIGSEGV, SIGBUS, SIGFPE, SIGILL, SIGTRAP };
+#endif
+
+#define NUM_HANDLED_SIGNALS (sizeof(HandledSignals) / sizeof(HandledSignals[0]))
+
+static struct sigaction PreviousActions[NUM_HANDLED_SIGNALS];
+static pthread_once_t InstallOnce = PTHREAD_ONCE_INIT;
+
+// Size of the alternate signal stacks allocated for the threads that have none, which must fit
+// the signal frame with the full vector state, the handler and the filters.
+#define SIGNAL_STACK_SIZE 0x10000
+
+// Distance below the stack pointer within which a memory fault is considered a stack overflow,
+// which covers the probes of a frame that spans a few pages.
+#define STACK_OVERFLOW_DISTANCE 0x10000
+
+static size_t PageSize;
+
+// Frees the alternate signal stack that was allocated for a thread when the thread exits.
+static pthread_key_t SignalStackKey;
+
+// Whether the current thread was checked for an alternate signal stack.
+static __thread int SignalStackChecked = 0;
+
+// Innermost guarded region of the current thread, or NULL if there is none.
+static __thread PFRAME CurrentFrame = NULL;
+
+// Software exception that is being raised by the current thread and the signal carrying it, if
+// any. Only read by the signal handler, so the signal must not be optimized away around raise.
+static __thread RECORD PendingRecord;
+static __thread volatile sig_atomic_t PendingSignal = 0;
+
+// Whether the software exception being raised was passed on to the previous handlers, as no
+// guarded region handled it.
+static __thread volatile sig_atomic_t PendingForwarded = 0;
+
+static uint32_t TranslateSignal(int Signal, const siginfo_t* Info)
+{
+    switch (Signal)
+    {
+    case SIGSEGV:
+        return STATUS_ACCESS_VIOLATION;
+    case SIGBUS:
+        return Info->si_code == BUS_ADRALN
+            ? STATUS_DATATYPE_MISALIGNMENT
+            : STATUS_IN_PAGE_ERROR;
+    case SIGFPE:
+        switch (Info->si_code)
+        {
+        case FPE_INTDIV: return STATUS_INTEGER_DIVIDE_BY_ZERO;
+        case FPE_INTOVF: return STATUS_INTEGER_OVERFLOW;
+        case FPE_FLTDIV: return STATUS_FLOAT_DIVIDE_BY_ZERO;
+        case FPE_FLTOVF: return STATUS_FLOAT_OVERFLOW;
+        case FPE_FLTUND: return STATUS_FLOAT_UNDERFLOW;
+        case FPE_FLTRES: return STATUS_FLOAT_INEXACT_RESULT;
+        case FPE_FLTSUB: return STATUS_ARRAY_BOUNDS_EXCEEDED;
+        default: return STATUS_FLOAT_INVALID_OPERATION;
+        }
+    case SIGILL:
+        return Info->si_code == ILL_PRVOPC || Info->si_code == ILL_PRVREG
+            ? STATUS_PRIVILEGED_INSTRUCTION
+            : STATUS_ILLEGAL_INSTRUCTION;
+    case SIGTRAP:
+        return Info->si_code == TRAP_TRACE
+            ? STATUS_SINGLE_STEP
+            : STATUS_BREAKPOINT;
+    default:
+        return 0;
+    }
+}
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+static void CopyRegisters(const ucontext_t* Context, uintptr_t* Registers)
+{
+#if defined(__linux__) && TG_ARCH == TG_ARCH_X86
+    const greg_t* Gregs = Context->uc_mcontext.gregs;
+
+    Registers[0] = (uintptr_t)Gregs[REG_EAX];
+    Registers[1] = (uintptr_t)Gregs[REG_EBX];
+    Registers[2] = (uintptr_t)Gregs[REG_ECX];
+    Registers[3] = (uintptr_t)Gregs[REG_EDX];
+    Registers[4] = (uintptr_t)Gregs[REG_ESI];
+    Registers[5] = (uintptr_t)Gregs[REG_EDI];
+    Registers[6] = (uintptr_t)Gregs[REG_EBP];
+    Registers[7] = (uintptr_t)Gregs[REG_ESP];
+    Registers[8] = (uintptr_t)Gregs[REG_EIP];
+    Registers[9] = (uintptr_t)Gregs[REG_EFL];
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_X64
+    const greg_t* Gregs = Context->uc_mcontext.gregs;
+
+    Registers[0] = (uintptr_t)Gregs[REG_RAX];
+    Registers[1] = (uintptr_t)Gregs[REG_RBX];
+    Registers[2] = (uintptr_t)Gregs[REG_RCX];
+    Registers[3] = (uintptr_t)Gregs[REG_RDX];
+    Registers[4] = (uintptr_t)Gregs[REG_RSI];
+    Registers[5] = (uintptr_t)Gregs[REG_RDI];
+    Registers[6] = (uintptr_t)Gregs[REG_RBP];
+    Registers[7] = (uintptr_t)Gregs[REG_RSP];
+    Registers[8] = (uintptr_t)Gregs[REG_R8];
+    Registers[9] = (uintptr_t)Gregs[REG_R9];
+    Registers[10] = (uintptr_t)Gregs[REG_R10];
+    Registers[11] = (uintptr_t)Gregs[REG_R11];
+    Registers[12] = (uintptr_t)Gregs[REG_R12];
+    Registers[13] = (uintptr_t)Gregs[REG_R13];
+    Registers[14] = (uintptr_t)Gregs[REG_R14];
+    Registers[15] = (uintptr_t)Gregs[REG_R15];
+    Registers[16] = (uintptr_t)Gregs[REG_RIP];
+    Registers[17] = (uintptr_t)Gregs[REG_EFL];
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_ARM64
+    // X29 and X30 are the frame pointer and the link register.
+    for (int i = 0; i < 31; ++i)
+    {
+        Registers[i] = (uintptr_t)Context->uc_mcontext.regs[i];
+    }
+
+    Registers[31] = (uintptr_t)Context->uc_mcontext.sp;
+    Registers[32] = (uintptr_t)Context->uc_mcontext.pc;
+    Registers[33] = (uintptr_t)Context->uc_mcontext.pstate;
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_X64
+    const _STRUCT_X86_THREAD_STATE64* State = &Context->uc_mcontext->__ss;
+
+    Registers[0] = State->__rax;
+    Registers[1] = State->__rbx;
+    Registers[2] = State->__rcx;
+    Registers[3] = State->__rdx;
+    Registers[4] = State->__rsi;
+    Registers[5] = State->__rdi;
+    Registers[6] = State->__rbp;
+    Registers[7] = State->__rsp;
+    Registers[8] = State->__r8;
+    Registers[9] = State->__r9;
+    Registers[10] = State->__r10;
+    Registers[11] = State->__r11;
+    Registers[12] = State->__r12;
+    Registers[13] = State->__r13;
+    Registers[14] = State->__r14;
+    Registers[15] = State->__r15;
+    Registers[16] = State->__rip;
+    Registers[17] = State->__rflags;
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_ARM64
+    const _STRUCT_ARM_THREAD_STATE64* State = &Context->uc_mcontext->__ss;
+
+    for (int i = 0; i < 29; ++i)
+    {
+        Registers[i] = State->__x[i];
+    }
+
+    Registers[29] = State->__fp;
+    Registers[30] = State->__lr;
+    Registers[31] = State->__sp;
+    Registers[32] = State->__pc;
+    Registers[33] = State->__cpsr;
+#else
+    (void)Context;
+    (void)Registers;
+#endif
+}
+#endif
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+// Writes the registers back to the context, the opposite of CopyRegisters.
+static void ApplyRegisters(ucontext_t* Context, const uintptr_t* Registers)
+{
+#if defined(__linux__) && TG_ARCH == TG_ARCH_X86
+    greg_t* Gregs = Context->uc_mcontext.gregs;
+
+    Gregs[REG_EAX] = (greg_t)Registers[0];
+    Gregs[REG_EBX] = (greg_t)Registers[1];
+    Gregs[REG_ECX] = (greg_t)Registers[2];
+    Gregs[REG_EDX] = (greg_t)Registers[3];
+    Gregs[REG_ESI] = (greg_t)Registers[4];
+    Gregs[REG_EDI] = (greg_t)Registers[5];
+    Gregs[REG_EBP] = (greg_t)Registers[6];
+    Gregs[REG_ESP] = (greg_t)Registers[7];
+    Gregs[REG_EIP] = (greg_t)Registers[8];
+    Gregs[REG_EFL] = (greg_t)Registers[9];
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_X64
+    greg_t* Gregs = Context->uc_mcontext.gregs;
+
+    Gregs[REG_RAX] = (greg_t)Registers[0];
+    Gregs[REG_RBX] = (greg_t)Registers[1];
+    Gregs[REG_RCX] = (greg_t)Registers[2];
+    Gregs[REG_RDX] = (greg_t)Registers[3];
+    Gregs[REG_RSI] = (greg_t)Registers[4];
+    Gregs[REG_RDI] = (greg_t)Registers[5];
+    Gregs[REG_RBP] = (greg_t)Registers[6];
+    Gregs[REG_RSP] = (greg_t)Registers[7];
+    Gregs[REG_R8] = (greg_t)Registers[8];
+    Gregs[REG_R9] = (greg_t)Registers[9];
+    Gregs[REG_R10] = (greg_t)Registers[10];
+    Gregs[REG_R11] = (greg_t)Registers[11];
+    Gregs[REG_R12] = (greg_t)Registers[12];
+    Gregs[REG_R13] = (greg_t)Registers[13];
+    Gregs[REG_R14] = (greg_t)Registers[14];
+    Gregs[REG_R15] = (greg_t)Registers[15];
+    Gregs[REG_RIP] = (greg_t)Registers[16];
+    Gregs[REG_EFL] = (greg_t)Registers[17];
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_ARM64
+    for (int i = 0; i < 31; ++i)
+    {
+        Context->uc_mcontext.regs[i] = Registers[i];
+    }
+
+    Context->uc_mcontext.sp = Registers[31];
+    Context->uc_mcontext.pc = Registers[32];
+    Context->uc_mcontext.pstate = Registers[33];
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_X64
+    _STRUCT_X86_THREAD_STATE64* State = &Context->uc_mcontext->__ss;
+
+    State->__rax = Registers[0];
+    State->__rbx = Registers[1];
+    State->__rcx = Registers[2];
+    State->__rdx = Registers[3];
+    State->__rsi = Registers[4];
+    State->__rdi = Registers[5];
+    State->__rbp = Registers[6];
+    State->__rsp = Registers[7];
+    State->__r8 = Registers[8];
+    State->__r9 = Registers[9];
+    State->__r10 = Registers[10];
+    State->__r11 = Registers[11];
+    State->__r12 = Registers[12];
+    State->__r13 = Registers[13];
+    State->__r14 = Registers[14];
+    State->__r15 = Registers[15];
+    State->__rip = Registers[16];
+    State->__rflags = Registers[17];
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_ARM64
+    _STRUCT_ARM_THREAD_STATE64* State = &Context->uc_mcontext->__ss;
+
+    for (int i = 0; i < 29; ++i)
+    {
+        State->__x[i] = Registers[i];
+    }
+
+    State->__fp = Registers[29];
+    State->__lr = Registers[30];
+    State->__sp = Registers[31];
+    State->__pc = Registers[32];
+    State->__cpsr = (uint32_t)Registers[33];
+#else
+    (void)Context;
+    (void)Registers;
+#endif
+}
+#endif
+
+#if defined(__linux__) && TG_ARCH == TG_ARCH_ARM64
+// Magic numbers of the records stored in the reserved area of the machine context.
+#define FPSIMD_MAGIC 0x46508001
+#define ESR_MAGIC 0x45535201
+
+// Looks for the record with the given magic number in the reserved area of the context.
+static const unsigned char* FindContextRecord(const ucontext_t* Context, uint32_t Magic)
+{
+    const unsigned char* Reserved = (const unsigned char*)Context->uc_mcontext.__reserved;
+    size_t Offset = 0;
+
+    while (Offset + 16 <= sizeof(Context->uc_mcontext.__reserved))
+    {
+        uint32_t Current, Size;
+        memcpy(&Current, Reserved + Offset, sizeof(Current));
+        memcpy(&Size, Reserved + Offset + 4, sizeof(Size));
+
+        if (Current == 0 || Size == 0)
+        {
+            break;
+        }
+
+        if (Current == Magic)
+        {
+            return Reserved + Offset;
+        }
+
+        Offset += Size;
+    }
+
+    return NULL;
+}
+#endif
+
+#if TG_ARCH == TG_ARCH_X86 || TG_ARCH == TG_ARCH_X64
+// Number of vector registers that are available to the architecture.
+#if TG_ARCH == TG_ARCH_X86
+#define NUM_VECTOR_REGISTERS 8
+#else
+#define NUM_VECTOR_REGISTERS 16
+#endif
+
+// Marks a FXSAVE image that is followed by an XSAVE area, in its software reserved bytes.
+#ifndef FP_XSTATE_MAGIC1
+#define FP_XSTATE_MAGIC1 0x46505853
+#endif
+
+// Components of the XSAVE area that hold the upper parts of the vector registers.
+#define XFEATURE_YMM 2
+#define XFEATURE_OPMASK 5
+#define XFEATURE_ZMM_HI256 6
+#define XFEATURE_HI16_ZMM 7
+
+// Contents of the components in their initial state, large enough for the biggest of them.
+static const unsigned char InitialXState[1024];
+
+static void CopyLegacyState(const unsigned char* Image, PFPU_STATE Fpu)
+{
+    Fpu->Features |= MS_FPU_X87 | MS_FPU_SSE;
+    memcpy(&Fpu->ControlWord, Image, sizeof(Fpu->ControlWord));
+    memcpy(&Fpu->StatusWord, Image + 2, sizeof(Fpu->StatusWord));
+    Fpu->TagWord = Image[4];
+    memcpy(&Fpu->Mxcsr, Image + 24, sizeof(Fpu->Mxcsr));
+
+    for (int i = 0; i < 8; ++i)
+    {
+        memcpy(Fpu->St[i], Image + 32 + i * 16, 16);
+    }
+
+    for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+    {
+        memcpy(Fpu->Zmm[i], Image + 160 + i * 16, 16);
+    }
+}
+
+#if defined(__linux__)
+// Locates a component in the XSAVE area that follows the FXSAVE image. The kernel saves the
+// area in the standard format, so the offsets are the ones reported by the processor.
+static const unsigned char* FindXStateFeature(const unsigned char* Image, uint32_t Feature)
+{
+    uint32_t Magic, Size, Eax, Ebx, Ecx, Edx;
+    uint64_t Enabled, Present;
+
+    memcpy(&Magic, Image + 464, sizeof(Magic));
+    memcpy(&Size, Image + 468, sizeof(Size));
+    memcpy(&Enabled, Image + 472, sizeof(Enabled));
+
+    if (Magic != FP_XSTATE_MAGIC1 || !(Enabled & (1ULL << Feature)))
+    {
+        return NULL;
+    }
+
+    if (!__get_cpuid_count(0xD, Feature, &Eax, &Ebx, &Ecx, &Edx) || Ebx + Eax > Size)
+    {
+        return NULL;
+    }
+
+    // Components in their initial state are not written, and are all zeros.
+    memcpy(&Present, Image + 512, sizeof(Present));
+    return (Present & (1ULL << Feature)) ? Image + Ebx : InitialXState;
+}
+
+static void CopyExtendedState(const unsigned char* Image, PFPU_STATE Fpu)
+{
+    const unsigned char* Feature = FindXStateFeature(Image, XFEATURE_YMM);
+    if (Feature == NULL)
+    {
+        return;
+    }
+
+    Fpu->Features |= MS_FPU_AVX;
+    for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+    {
+        memcpy(&Fpu->Zmm[i][2], Feature + i * 16, 16);
+    }
+
+    Feature = FindXStateFeature(Image, XFEATURE_OPMASK);
+    if (Feature == NULL)
+    {
+        return;
+    }
+
+    Fpu->Features |= MS_FPU_AVX512;
+    memcpy(Fpu->Opmask, Feature, sizeof(Fpu->Opmask));
+
+    Feature = FindXStateFeature(Image, XFEATURE_ZMM_HI256);
+    if (Feature != NULL)
+    {
+        for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+        {
+            memcpy(&Fpu->Zmm[i][4], Feature + i * 32, 32);
+        }
+    }
+
+#if TG_ARCH == TG_ARCH_X64
+    Feature = FindXStateFeature(Image, XFEATURE_HI16_ZMM);
+    if (Feature != NULL)
+    {
+        for (int i = 0; i < 16; ++i)
+        {
+            memcpy(Fpu->Zmm[16 + i], Feature + i * 64, 64);
+        }
+    }
+#endif
+}
+#endif
+#endif
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+static void CopyFpuState(const ucontext_t* Context, PFPU_STATE Fpu)
+{
+#if defined(__linux__) && TG_ARCH == TG_ARCH_X64
+    const unsigned char* Image = (const unsigned char*)Context->uc_mcontext.fpregs;
+
+    if (Image != NULL)
+    {
+        CopyLegacyState(Image, Fpu);
+        CopyExtendedState(Image, Fpu);
+    }
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_X86
+    // The FXSAVE image follows the legacy x87 state, which ends with a magic number that tells
+    // whether the image is present.
+    const unsigned char* Legacy = (const unsigned char*)Context->uc_mcontext.fpregs;
+
+    if (Legacy != NULL)
+    {
+        uint16_t Magic;
+        memcpy(&Magic, Legacy + 110, sizeof(Magic));
+
+        if (Magic == 0)
+        {
+            CopyLegacyState(Legacy + 112, Fpu);
+            CopyExtendedState(Legacy + 112, Fpu);
+        }
+    }
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_ARM64
+    const unsigned char* Record = FindContextRecord(Context, FPSIMD_MAGIC);
+
+    if (Record != NULL)
+    {
+        Fpu->Features = MS_FPU_NEON;
+        memcpy(&Fpu->Fpsr, Record + 8, sizeof(Fpu->Fpsr));
+        memcpy(&Fpu->Fpcr, Record + 12, sizeof(Fpu->Fpcr));
+        memcpy(Fpu->V, Record + 16, sizeof(Fpu->V));
+    }
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_X64
+    // The float state holds a FXSAVE image after its reserved header.
+    CopyLegacyState((const unsigned char*)&Context->uc_mcontext->__fs.__fpu_fcw, Fpu);
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_ARM64
+    const _STRUCT_ARM_NEON_STATE64* State = &Context->uc_mcontext->__ns;
+
+    Fpu->Features = MS_FPU_NEON;
+    Fpu->Fpsr = State->__fpsr;
+    Fpu->Fpcr = State->__fpcr;
+    memcpy(Fpu->V, State->__v, sizeof(Fpu->V));
+#else
+    (void)Context;
+    (void)Fpu;
+#endif
+}
+#endif
+
+// Determines whether a memory fault was caused by a read, a write or an instruction fetch,
+// falling back to a read when the platform does not tell.
+static uintptr_t GetAccessKind(const ucontext_t* Context)
+{
+#if defined(__linux__) && (TG_ARCH == TG_ARCH_X86 || TG_ARCH == TG_ARCH_X64)
+    // The error code is only meaningful for page faults (#PF, vector 14).
+    if (Context->uc_mcontext.gregs[REG_TRAPNO] == 14)
+    {
+        greg_t Error = Context->uc_mcontext.gregs[REG_ERR];
+        if (Error & 0x10)
+        {
+            return MS_ACCESS_EXECUTE;
+        }
+        if (Error & 0x2)
+        {
+            return MS_ACCESS_WRITE;
+        }
+    }
+#elif defined(__linux__) && TG_ARCH == TG_ARCH_ARM64
+    // The syndrome register of the fault is stored in one of the records of the reserved area.
+    const unsigned char* Record = FindContextRecord(Context, ESR_MAGIC);
+    if (Record != NULL)
+    {
+        uint64_t Esr;
+        memcpy(&Esr, Record + 8, sizeof(Esr));
+
+        switch ((Esr >> 26) & 0x3F)
+        {
+        case 0x20: // Instruction abort from a lower exception level.
+        case 0x21:
+            return MS_ACCESS_EXECUTE;
+        case 0x24: // Data abort from a lower exception level.
+        case 0x25:
+            return (Esr & (1 << 6)) ? MS_ACCESS_WRITE : MS_ACCESS_READ;
+        }
+    }
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_X64
+    uint32_t Error = Context->uc_mcontext->__es.__err;
+    if (Error & 0x10)
+    {
+        return MS_ACCESS_EXECUTE;
+    }
+    if (Error & 0x2)
+    {
+        return MS_ACCESS_WRITE;
+    }
+#elif defined(__APPLE__) && TG_ARCH == TG_ARCH_ARM64
+    uint32_t Esr = Context->uc_mcontext->__es.__esr;
+    switch ((Esr >> 26) & 0x3F)
+    {
+    case 0x20:
+    case 0x21:
+        return MS_ACCESS_EXECUTE;
+    case 0x24:
+    case 0x25:
+        return (Esr & (1 << 6)) ? MS_ACCESS_WRITE : MS_ACCESS_READ;
+    }
+#else
+    (void)Context;
+#endif
+
+    return MS_ACCESS_READ;
+}
+
+// Memory is read in chunks that never cross a page boundary, as pages are either readable or not.
+#define READ_CHUNK_SIZE 0x1000
+
+// Reads made for the snapshots of an exception. The kernel reports unreadable memory with EFAULT
+// instead of a signal, so the bytes are copied by a system call rather than read directly. A pipe
+// is only opened when process_vm_readv is not available, and is shared by every read.
+typedef struct _MEMORY_READER
+{
+    int Pipe[2];
+    int PipeOpened;
+} MEMORY_READER, *PMEMORY_READER;
+
+#if defined(__linux__)
+// Whether process_vm_readv can be used, which a seccomp policy or an old kernel may prevent.
+static volatile int VectoredReadsDisabled = 0;
+#endif
+
+static int ReadChunk(PMEMORY_READER Reader, uintptr_t Address, uint8_t* Buffer, uint32_t Size)
+{
+#if defined(__linux__)
+    if (!VectoredReadsDisabled)
+    {
+        struct iovec Local = { Buffer, Size };
+        struct iovec Remote = { (void*)Address, Size };
+        ssize_t Read = process_vm_readv(getpid(), &Local, 1, &Remote, 1, 0);
+
+        if (Read >= 0 || errno == EFAULT)
+        {
+            return Read == (ssize_t)Size;
+        }
+
+        VectoredReadsDisabled = 1;
+    }
+#endif
+
+    if (!Reader->PipeOpened)
+    {
+#if defined(__linux__)
+        if (pipe2(Reader->Pipe, O_CLOEXEC) != 0)
+#else
+        if (pipe(Reader->Pipe) != 0)
+#endif
+        {
+            return 0;
+        }
+
+        Reader->PipeOpened = 1;
+    }
+
+    return write(Reader->Pipe[1], (const void*)Address, Size) == (ssize_t)Size &&
+           read(Reader->Pipe[0], Buffer, Size) == (ssize_t)Size;
+}
+
+// Reads as many bytes as possible from the given address, without letting a failed read
+// raise another signal.
+static uint32_t ReadMemory(PMEMORY_READER Reader, uintptr_t Address, uint8_t* Buffer, uint32_t Size)
+{
+    uint32_t Length = 0;
+
+    while (Length < Size)
+    {
+        uintptr_t Current = Address + Length;
+        uint32_t Chunk = READ_CHUNK_SIZE - (uint32_t)(Current & (READ_CHUNK_SIZE - 1));
+
+        if (Chunk > Size - Length)
+        {
+            Chunk = Size - Length;
+        }
+
+        if (Current < Address || !ReadChunk(Reader, Current, Buffer + Length, Chunk))
+        {
+            break;
+        }
+
+        Length += Chunk;
+    }
+
+    return Length;
+}
+
+static void CaptureMemory(PEXCEPTION Exception)
+{
+    MEMORY_READER Reader;
+
+    Reader.PipeOpened = 0;
+
+    Exception->Code.Address = Exception->Record.Address;
+    Exception->Code.Length = ReadMemory(&Reader, Exception->Code.Address, Exception->Code.Bytes, MS_CODE_SNAPSHOT_SIZE);
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+    Exception->Stack.Address = Exception->Registers[MS_REG_SP];
+    Exception->Stack.Length = ReadMemory(&Reader, Exception->Stack.Address, Exception->Stack.Bytes, MS_STACK_SNAPSHOT_SIZE);
+#endif
+
+    // Memory faults report the address of the data in their second parameter, and the window
+    // is centered on it, even though the bytes past it usually cannot be read.
+    switch (Exception->Record.Code)
+    {
+    case STATUS_ACCESS_VIOLATION:
+    case STATUS_IN_PAGE_ERROR:
+        if (Exception->Record.NumberParameters >= 2)
+        {
+            uintptr_t Data = Exception->Record.Information[1];
+
+            Exception->Data.Address = Data > MS_DATA_SNAPSHOT_SIZE / 2 ? Data - MS_DATA_SNAPSHOT_SIZE / 2 : 0;
+            Exception->Data.Length = ReadMemory(&Reader, Exception->Data.Address, Exception->Data.Bytes, MS_DATA_SNAPSHOT_SIZE);
+        }
+        break;
+    }
+
+    if (Reader.PipeOpened)
+    {
+        close(Reader.Pipe[0]);
+        close(Reader.Pipe[1]);
+    }
+}
+
+static int IsSoftwareException(int Signal, const siginfo_t* Info)
+{
+    if (PendingSignal == 0 || Signal != PendingSignal)
+    {
+        return 0;
+    }
+
+#if defined(SI_TKILL)
+    return Info->si_code == SI_USER || Info->si_code == SI_TKILL;
+#else
+    return Info->si_code == SI_USER;
+#endif
+}
+
+// Faults on the pages right below the stack pointer come from a stack that cannot grow anymore,
+// either because it reached its guard page or the limit of the main thread.
+static int IsStackOverflow(const siginfo_t* Info, uintptr_t StackPointer)
+{
+    uintptr_t Address = (uintptr_t)Info->si_addr;
+
+    // Only faults reported by the kernel, not signals sent by a process.
+    if (Info->si_code <= 0 || StackPointer == 0)
+    {
+        return 0;
+    }
+
+    return Address < StackPointer + PageSize && Address + STACK_OVERFLOW_DISTANCE >= StackPointer;
+}
+
+static void FillException(int Signal, const siginfo_t* Info, const ucontext_t* Context, PEXCEPTION Exception, uint32_t Flags)
+{
+    // The same exception may be filled more than once, if execution was continued.
+    memset(Exception, 0, sizeof(*Exception));
+
+    Exception->Record.Code = TranslateSignal(Signal, Info);
+    Exception->SignalInfo.Signal = Signal;
+    Exception->SignalInfo.Code = Info->si_code;
+    Exception->SignalInfo.Errno = Info->si_errno;
+    Exception->SignalInfo.Address = (uintptr_t)Info->si_addr;
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+    CopyRegisters(Context, Exception->Registers);
+    CopyFpuState(Context, &Exception->Fpu);
+    Exception->Record.Address = Exception->Registers[MS_REG_PC];
+#endif
+
+    switch (Signal)
+    {
+    case SIGSEGV:
+    case SIGBUS:
+        // Memory faults carry the same parameters as their Windows counterparts.
+        if (Exception->Record.Code != STATUS_DATATYPE_MISALIGNMENT)
+        {
+            Exception->Record.NumberParameters = 2;
+            Exception->Record.Information[0] = GetAccessKind(Context);
+            Exception->Record.Information[1] = (uintptr_t)Info->si_addr;
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+            if (IsStackOverflow(Info, Exception->Registers[MS_REG_SP]))
+            {
+                Exception->Record.Code = STATUS_STACK_OVERFLOW;
+            }
+#endif
+        }
+        break;
+    case SIGTRAP:
+#if (TG_ARCH == TG_ARCH_X86 || TG_ARCH == TG_ARCH_X64) && defined(SI_KERNEL)
+        // `int3` leaves the program counter past the instruction, while Windows reports the
+        // address of the breakpoint itself.
+        if (Info->si_code == SI_KERNEL && Exception->Record.Address != 0)
+        {
+            Exception->Record.Address -= 1;
+        }
+#endif
+        break;
+    }
+
+    if (Exception->Record.Address == 0)
+    {
+        Exception->Record.Address = (uintptr_t)Info->si_addr;
+    }
+
+    // Software exceptions are raised by sending a signal to the current thread, and carry the
+    // record that was given to RaiseRecord.
+    if (IsSoftwareException(Signal, Info))
+    {
+        Exception->Record = PendingRecord;
+    }
+
+    if ((Flags & MS_CAPTURE_MEMORY) != 0)
+    {
+        CaptureMemory(Exception);
+    }
+}
+
+// Hands a signal that did not originate in a guarded region to whoever was handling it
+// before us, falling back to the default disposition (usually terminating the process).
+static void ForwardSignal(size_t Index, int Signal, siginfo_t* Info, void* Context)
+{
+    const struct sigaction* Previous = &PreviousActions[Index];
+
+    if (Previous->sa_flags & SA_SIGINFO)
+    {
+        if (Previous->sa_sigaction != NULL)
+        {
+            Previous->sa_sigaction(Signal, Info, Context);
+            return;
+        }
+    }
+    else if (Previous->sa_handler != SIG_DFL && Previous->sa_handler != SIG_IGN)
+    {
+        Previous->sa_handler(Signal);
+        return;
+    }
+
+    // The signal is blocked while we are in the handler, so it will be delivered with the
+    // default disposition as soon as we return.
+    signal(Signal, SIG_DFL);
+    raise(Signal);
+}
+
+// Leaves the regions that are nested in the given one, innermost first, running their finalizers
+// like the termination handlers that are run when the stack is unwound on Windows.
+static void RunFinalizers(PFRAME Target)
+{
+    while (CurrentFrame != NULL && CurrentFrame != Target)
+    {
+        PFRAME Frame = CurrentFrame;
+
+        // A finalizer is only run once, even if it raises an exception that is handled.
+        CurrentFrame = Frame->Previous;
+        if (Frame->FinalizerExecutor != NULL)
+        {
+            Frame->FinalizerExecutor(Frame->Finalizer);
+        }
+    }
+}
+
+// Hands a signal that no guarded region handled to whoever was handling it before us.
+static void PassOnSignal(int Signal, siginfo_t* Info, void* Context)
+{
+    for (size_t i = 0; i < NUM_HANDLED_SIGNALS; ++i)
+    {
+        if (HandledSignals[i] == Signal)
+        {
+            ForwardSignal(i, Signal, Info, Context);
+            break;
+        }
+    }
+}
+
+static void SignalHandler(int Signal, siginfo_t* Info, void* Context)
+{
+    // Capturing the exception may clobber errno, which belongs to the interrupted code.
+    int SavedErrno = errno;
+
+#if defined(SIGRTMIN)
+    // The signal of the software exceptions is only an exception when it was raised for one, and
+    // otherwise belongs to the program.
+    if (Signal == SOFTWARE_SIGNAL && !IsSoftwareException(Signal, Info))
+    {
+        PassOnSignal(Signal, Info, Context);
+        return;
+    }
+#endif
+
+    // Guarded regions are searched from the innermost outwards, like nested __try blocks.
+    for (PFRAME Frame = CurrentFrame; Frame != NULL; Frame = Frame->Previous)
+    {
+        int32_t Disposition = MS_EXECUTE_HANDLER;
+
+        if (Frame->FinalizerExecutor != NULL)
+        {
+            continue;
+        }
+
+        if (Frame->Exception != NULL)
+        {
+            FillException(Signal, Info, (const ucontext_t*)Context, Frame->Exception, Frame->Flags);
+
+            if (Frame->FilterExecutor != NULL)
+            {
+                Disposition = Frame->FilterExecutor(Frame->Filter, Frame->Exception);
+            }
+        }
+
+        if (Disposition == MS_EXECUTE_HANDLER)
+        {
+            PendingSignal = 0;
+            RunFinalizers(Frame);
+
+            // Restores the signal mask saved by sigsetjmp, unblocking the signal we are handling.
+            siglongjmp(Frame->Env, 1);
+        }
+
+        if (Disposition == MS_CONTINUE_EXECUTION)
+        {
+#if TG_ARCH != TG_ARCH_UNKNOWN
+            // The filter may have changed the registers the execution continues with.
+            if (Frame->Exception != NULL)
+            {
+                ApplyRegisters((ucontext_t*)Context, Frame->Exception->Registers);
+            }
+#endif
+
+            errno = SavedErrno;
+            return;
+        }
+    }
+
+    errno = SavedErrno;
+    PendingForwarded = IsSoftwareException(Signal, Info);
+    PendingSignal = 0;
+    PassOnSignal(Signal, Info, Context);
+}
+
+static void FreeSignalStack(void* Stack)
+{
+    stack_t Current;
+
+    // The thread may have replaced the alternate stack in the meantime.
+    if (sigaltstack(NULL, &Current) == 0 && Current.ss_sp == (char*)Stack + PageSize)
+    {
+        stack_t Disable;
+
+        memset(&Disable, 0, sizeof(Disable));
+        Disable.ss_flags = SS_DISABLE;
+        sigaltstack(&Disable, NULL);
+    }
+
+    munmap(Stack, SIGNAL_STACK_SIZE + PageSize);
+}
+
+// Makes sure that the signal handler has a stack to run on when the stack of the thread has
+// overflowed. Threads spawned by Rust already have one, while foreign threads usually do not.
+static void EnsureSignalStack(void)
+{
+    stack_t Current;
+    stack_t Stack;
+    void* Memory;
+
+    if (SignalStackChecked)
+    {
+        return;
+    }
+
+    SignalStackChecked = 1;
+    if (sigaltstack(NULL, &Current) != 0 || (Current.ss_flags & SS_DISABLE) == 0)
+    {
+        return;
+    }
+
+    Memory = mmap(NULL, SIGNAL_STACK_SIZE + PageSize, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (Memory == MAP_FAILED)
+    {
+        return;
+    }
+
+    // The lowest page is a guard, so that overflowing the signal stack does not go unnoticed.
+    mprotect(Memory, PageSize, PROT_NONE);
+
+    memset(&Stack, 0, sizeof(Stack));
+    Stack.ss_sp = (char*)Memory + PageSize;
+    Stack.ss_size = SIGNAL_STACK_SIZE;
+
+    if (sigaltstack(&Stack, NULL) != 0)
+    {
+        munmap(Memory, SIGNAL_STACK_SIZE + PageSize);
+        return;
+    }
+
+    pthread_setspecific(SignalStackKey, Memory);
+}
+
+static void InstallHandlers(void)
+{
+    struct sigaction Action;
+
+    PageSize = (size_t)sysconf(_SC_PAGESIZE);
+    pthread_key_create(&SignalStackKey, FreeSignalStack);
+
+    memset(&Action, 0, sizeof(Action));
+    Action.sa_sigaction = SignalHandler;
+    Action.sa_flags = SA_SIGINFO | SA_ONSTACK;
+    sigemptyset(&Action.sa_mask);
+
+#if defined(SIGRTMIN)
+    HandledSignals[NUM_HANDLED_SIGNALS - 1] = SOFTWARE_SIGNAL;
+#endif
+
+    for (size_t i = 0; i < NUM_HANDLED_SIGNALS; ++i)
+    {
+        sigaction(HandledSignals[i], &Action, &PreviousActions[i]);
+    }
+}
+
+uint32_t __microseh_HandlerStub(
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PFILTER_EXECUTOR FilterExecutor,
+    void* Filter,
+    PEXCEPTION Exception,
+    uint32_t Flags
+) {
+    uint32_t Result = MS_SUCCEEDED;
+    FRAME Frame;
+
+    pthread_once(&InstallOnce, InstallHandlers);
+    EnsureSignalStack();
+
+    Frame.FilterExecutor = FilterExecutor;
+    Frame.Filter = Filter;
+    Frame.Exception = Exception;
+    Frame.Flags = Flags;
+    Frame.FinalizerExecutor = NULL;
+    Frame.Finalizer = NULL;
+    Frame.Previous = CurrentFrame;
+
+    if (sigsetjmp(Frame.Env, 1) == 0)
+    {
+        CurrentFrame = &Frame;
+        ProcExecutor(Proc);
+    }
+    else
+    {
+        Result = MS_CATCHED;
+    }
+
+    CurrentFrame = Frame.Previous;
+    return Result;
+}
+
+void __microseh_FinallyStub(
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PPROC_EXECUTOR FinalizerExecutor,
+    void* Finalizer
+) {
+    FRAME Frame;
+
+    Frame.FilterExecutor = NULL;
+    Frame.Filter = NULL;
+    Frame.Exception = NULL;
+    Frame.Flags = 0;
+    Frame.FinalizerExecutor = FinalizerExecutor;
+    Frame.Finalizer = Finalizer;
+    Frame.Previous = CurrentFrame;
+
+    CurrentFrame = &Frame;
+    ProcExecutor(Proc);
+    CurrentFrame = Frame.Previous;
+
+    FinalizerExecutor(Finalizer);
+}
+
+// Sends the signal to the current thread, attaching the record to it. Returns only if a filter
+// continued the execution, or if a previous handler returned, in which case it returns 1.
+static int RaiseRecord(const RECORD* Record, int Signal)
+{
+    PendingRecord = *Record;
+    if (PendingRecord.NumberParameters > MS_MAXIMUM_PARAMETERS)
+    {
+        PendingRecord.NumberParameters = MS_MAXIMUM_PARAMETERS;
+    }
+
+    __atomic_signal_fence(__ATOMIC_SEQ_CST);
+    PendingForwarded = 0;
+    PendingSignal = Signal;
+    raise(Signal);
+    PendingSignal = 0;
+
+    return PendingForwarded;
+}
+
+// Raised when a filter continues the execution after a noncontinuable exception.
+__attribute__((noreturn)) static void RaiseNoncontinuable(void)
+{
+    RECORD Noncontinuable;
+
+    memset(&Noncontinuable, 0, sizeof(Noncontinuable));
+    Noncontinuable.Code = STATUS_NONCONTINUABLE_EXCEPTION;
+    Noncontinuable.Flags = EXCEPTION_NONCONTINUABLE;
+    Noncontinuable.Address = (uintptr_t)__builtin_return_address(0);
+
+    for (;;)
+    {
+        RaiseRecord(&Noncontinuable, SOFTWARE_SIGNAL);
+    }
+}
+
+void __microseh_RaiseException(const RECORD* Record)
+{
+    RECORD Software = *Record;
+    Software.Flags |= EXCEPTION_SOFTWARE_ORIGINATE;
+
+    // Like RaiseException, the address is the one of the caller when it is not given.
+    if (Software.Address == 0)
+    {
+        Software.Address = (uintptr_t)__builtin_return_address(0);
+    }
+
+    RaiseRecord(&Software, SOFTWARE_SIGNAL);
+
+    if (Record->Flags & EXCEPTION_NONCONTINUABLE)
+    {
+        RaiseNoncontinuable();
+    }
+}
+
+void __microseh_RethrowException(const EXCEPTION* Exception)
+{
+    // The original signal is sent again, so that the default action of the system is the same
+    // as if the exception had never been caught.
+    int Signal = Exception->SignalInfo.Signal != 0 ? Exception->SignalInfo.Signal : SOFTWARE_SIGNAL;
+
+    // A previous handler that returns expects the faulting instruction to be executed again, which
+    // raises the signal again, possibly with the default action restored by the handler.
+    while (RaiseRecord(&Exception->Record, Signal))
+    {
+    }
+
+    // The context the exception occurred in is gone, so there is nothing to continue.
+    RaiseNoncontinuable();
+}
+
+#if defined(__GLIBC__)
+// Call that is made on a separate stack, and its result.
+typedef struct _STACK_CALL
+{
+    PPROC_EXECUTOR ProcExecutor;
+    void* Proc;
+    PEXCEPTION Exception;
+    uint32_t Result;
+    ucontext_t Caller;
+} STACK_CALL, *PSTACK_CALL;
+
+// Call that is being made by the current thread, as makecontext can only pass integers.
+static __thread PSTACK_CALL PendingCall = NULL;
+
+// Returns to the caller through the link of the context once the procedure has been executed.
+static void StackEntry(void)
+{
+    PSTACK_CALL Call = PendingCall;
+
+    Call->Result = __microseh_HandlerStub(Call->ProcExecutor, Call->Proc, NULL, NULL, Call->Exception, 0);
+}
+
+// Pages are only backed by memory once they are touched, so the deepest resident page tells how
+// far the stack grew.
+static size_t GetStackUsage(uint8_t* Base, size_t Size)
+{
+    unsigned char Residency[256];
+    size_t Pages = Size / PageSize;
+
+    for (size_t First = 0; First < Pages; First += sizeof(Residency))
+    {
+        size_t Count = Pages - First < sizeof(Residency) ? Pages - First : sizeof(Residency);
+
+        if (mincore(Base + First * PageSize, Count * PageSize, Residency) != 0)
+        {
+            return 0;
+        }
+
+        for (size_t i = 0; i < Count; ++i)
+        {
+            if (Residency[i] & 1)
+            {
+                return (Pages - First - i) * PageSize;
+            }
+        }
+    }
+
+    return 0;
+}
+#endif
+
+uint32_t __microseh_StackStub(
+    size_t Size,
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PEXCEPTION Exception,
+    size_t* HighWaterMark
+) {
+    *HighWaterMark = 0;
+
+#if defined(__GLIBC__)
+    STACK_CALL Call;
+    ucontext_t Context;
+    uint8_t* Memory;
+
+    pthread_once(&InstallOnce, InstallHandlers);
+
+    if (Size < (size_t)PTHREAD_STACK_MIN)
+    {
+        Size = PTHREAD_STACK_MIN;
+    }
+
+    if (Size > SIZE_MAX - PageSize * 2)
+    {
+        return MS_STACK_UNAVAILABLE;
+    }
+
+    Size = (Size + PageSize - 1) & ~(PageSize - 1);
+    Memory = mmap(NULL, Size + PageSize, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE, -1, 0);
+    if (Memory == MAP_FAILED)
+    {
+        return MS_STACK_UNAVAILABLE;
+    }
+
+    // The lowest page is a guard, so that an overflow faults right below the stack pointer.
+    if (mprotect(Memory, PageSize, PROT_NONE) != 0 || getcontext(&Context) != 0)
+    {
+        munmap(Memory, Size + PageSize);
+        return MS_STACK_UNAVAILABLE;
+    }
+
+#if defined(MADV_NOHUGEPAGE)
+    // Huge pages are backed by memory all at once, which would hide how far the stack grew.
+    madvise(Memory + PageSize, Size, MADV_NOHUGEPAGE);
+#endif
+
+    Call.ProcExecutor = ProcExecutor;
+    Call.Proc = Proc;
+    Call.Exception = Exception;
+    Call.Result = MS_STACK_UNAVAILABLE;
+
+    Context.uc_stack.ss_sp = Memory + PageSize;
+    Context.uc_stack.ss_size = Size;
+    Context.uc_link = &Call.Caller;
+    makecontext(&Context, StackEntry, 0);
+
+    PendingCall = &Call;
+    if (swapcontext(&Call.Caller, &Context) == 0)
+    {
+        *HighWaterMark = GetStackUsage(Memory + PageSize, Size);
+    }
+
+    munmap(Memory, Size + PageSize);
+    return Call.Result;
+#else
+    // Contexts cannot be switched portably without makecontext, which is not available.
+    (void)Size;
+    (void)ProcExecutor;
+    (void)Proc;
+    (void)Exception;
+    return MS_STACK_UNAVAILABLE;
+#endif
+}
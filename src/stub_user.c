@@ -0,0 +1,449 @@
+// User-mode parts of the Windows backend, which depend on the Win32 API, ntdll and the C runtime. This
+// file is only built along with the `std` feature, so that stub.c can still be linked in kernel drivers.
+#include <windows.h>
+#include <malloc.h>
+#include <string.h>
+
+#include "stub.h"
+
+// Used to raise an exception with the address of the original one, which RaiseException overwrites.
+#if defined(_MSC_VER)
+#pragma comment(lib, "ntdll")
+#endif
+
+__declspec(dllimport) LONG NTAPI NtRaiseException(
+    PEXCEPTION_RECORD ExceptionRecord,
+    PCONTEXT ContextRecord,
+    BOOLEAN FirstChance
+);
+
+void __microseh_ResetStackGuard(void)
+{
+    _resetstkoflw();
+}
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+static void CopyRegisters(PCONTEXT Context, uintptr_t* Registers)
+{
+#if TG_ARCH == TG_ARCH_X86
+    Registers[0] = Context->Eax;
+    Registers[1] = Context->Ebx;
+    Registers[2] = Context->Ecx;
+    Registers[3] = Context->Edx;
+    Registers[4] = Context->Esi;
+    Registers[5] = Context->Edi;
+    Registers[6] = Context->Ebp;
+    Registers[7] = Context->Esp;
+    Registers[8] = Context->Eip;
+    Registers[9] = Context->EFlags;
+#elif TG_ARCH == TG_ARCH_X64
+    Registers[0] = Context->Rax;
+    Registers[1] = Context->Rbx;
+    Registers[2] = Context->Rcx;
+    Registers[3] = Context->Rdx;
+    Registers[4] = Context->Rsi;
+    Registers[5] = Context->Rdi;
+    Registers[6] = Context->Rbp;
+    Registers[7] = Context->Rsp;
+    Registers[8] = Context->R8;
+    Registers[9] = Context->R9;
+    Registers[10] = Context->R10;
+    Registers[11] = Context->R11;
+    Registers[12] = Context->R12;
+    Registers[13] = Context->R13;
+    Registers[14] = Context->R14;
+    Registers[15] = Context->R15;
+    Registers[16] = Context->Rip;
+    Registers[17] = Context->EFlags;
+#elif TG_ARCH == TG_ARCH_ARM64
+    // X29 and X30 are the frame pointer and the link register.
+    for (int i = 0; i < 31; ++i)
+    {
+        Registers[i] = Context->X[i];
+    }
+
+    Registers[31] = Context->Sp;
+    Registers[32] = Context->Pc;
+    Registers[33] = Context->Cpsr;
+#endif
+}
+
+// Writes the registers back to the context, the opposite of CopyRegisters.
+static void ApplyRegisters(PCONTEXT Context, const uintptr_t* Registers)
+{
+#if TG_ARCH == TG_ARCH_X86
+    Context->Eax = Registers[0];
+    Context->Ebx = Registers[1];
+    Context->Ecx = Registers[2];
+    Context->Edx = Registers[3];
+    Context->Esi = Registers[4];
+    Context->Edi = Registers[5];
+    Context->Ebp = Registers[6];
+    Context->Esp = Registers[7];
+    Context->Eip = Registers[8];
+    Context->EFlags = Registers[9];
+#elif TG_ARCH == TG_ARCH_X64
+    Context->Rax = Registers[0];
+    Context->Rbx = Registers[1];
+    Context->Rcx = Registers[2];
+    Context->Rdx = Registers[3];
+    Context->Rsi = Registers[4];
+    Context->Rdi = Registers[5];
+    Context->Rbp = Registers[6];
+    Context->Rsp = Registers[7];
+    Context->R8 = Registers[8];
+    Context->R9 = Registers[9];
+    Context->R10 = Registers[10];
+    Context->R11 = Registers[11];
+    Context->R12 = Registers[12];
+    Context->R13 = Registers[13];
+    Context->R14 = Registers[14];
+    Context->R15 = Registers[15];
+    Context->Rip = Registers[16];
+    Context->EFlags = (DWORD)Registers[17];
+#elif TG_ARCH == TG_ARCH_ARM64
+    for (int i = 0; i < 31; ++i)
+    {
+        Context->X[i] = Registers[i];
+    }
+
+    Context->Sp = Registers[31];
+    Context->Pc = Registers[32];
+    Context->Cpsr = (DWORD)Registers[33];
+#endif
+}
+
+#if TG_ARCH == TG_ARCH_X86 || TG_ARCH == TG_ARCH_X64
+// Number of vector registers that are available to the architecture.
+#if TG_ARCH == TG_ARCH_X86
+#define NUM_VECTOR_REGISTERS 8
+#else
+#define NUM_VECTOR_REGISTERS 16
+#endif
+
+static void CopyLegacyState(const XSAVE_FORMAT* Source, PFPU_STATE Fpu)
+{
+    Fpu->Features |= MS_FPU_X87 | MS_FPU_SSE;
+    Fpu->ControlWord = Source->ControlWord;
+    Fpu->StatusWord = Source->StatusWord;
+    Fpu->TagWord = Source->TagWord;
+    Fpu->Mxcsr = Source->MxCsr;
+
+    for (int i = 0; i < 8; ++i)
+    {
+        Fpu->St[i][0] = Source->FloatRegisters[i].Low;
+        Fpu->St[i][1] = (uint64_t)Source->FloatRegisters[i].High;
+    }
+
+    for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+    {
+        Fpu->Zmm[i][0] = Source->XmmRegisters[i].Low;
+        Fpu->Zmm[i][1] = (uint64_t)Source->XmmRegisters[i].High;
+    }
+}
+
+// Copies the upper parts of the vector registers that are stored in the extended state of
+// the context, if the context has any.
+static void CopyExtendedState(PCONTEXT Context, PFPU_STATE Fpu)
+{
+    const uint64_t* Feature;
+    DWORD Length;
+
+    if ((Context->ContextFlags & CONTEXT_XSTATE) != CONTEXT_XSTATE)
+    {
+        return;
+    }
+
+    // Upper 128 bits of the YMM registers.
+    Feature = (const uint64_t*)LocateXStateFeature(Context, XSTATE_AVX, &Length);
+    if (Feature == NULL)
+    {
+        return;
+    }
+
+    Fpu->Features |= MS_FPU_AVX;
+    for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+    {
+        Fpu->Zmm[i][2] = Feature[i * 2];
+        Fpu->Zmm[i][3] = Feature[i * 2 + 1];
+    }
+
+#if defined(XSTATE_AVX512_KMASK) && defined(XSTATE_AVX512_ZMM_H)
+    // Opmask registers and upper 256 bits of the first ZMM registers.
+    Feature = (const uint64_t*)LocateXStateFeature(Context, XSTATE_AVX512_KMASK, &Length);
+    if (Feature == NULL)
+    {
+        return;
+    }
+
+    Fpu->Features |= MS_FPU_AVX512;
+    for (int i = 0; i < 8; ++i)
+    {
+        Fpu->Opmask[i] = Feature[i];
+    }
+
+    Feature = (const uint64_t*)LocateXStateFeature(Context, XSTATE_AVX512_ZMM_H, &Length);
+    if (Feature != NULL)
+    {
+        for (int i = 0; i < NUM_VECTOR_REGISTERS; ++i)
+        {
+            for (int j = 0; j < 4; ++j)
+            {
+                Fpu->Zmm[i][4 + j] = Feature[i * 4 + j];
+            }
+        }
+    }
+
+#if TG_ARCH == TG_ARCH_X64 && defined(XSTATE_AVX512_ZMM)
+    // Full width of the ZMM16 to ZMM31 registers.
+    Feature = (const uint64_t*)LocateXStateFeature(Context, XSTATE_AVX512_ZMM, &Length);
+    if (Feature != NULL)
+    {
+        for (int i = 0; i < 16; ++i)
+        {
+            for (int j = 0; j < 8; ++j)
+            {
+                Fpu->Zmm[16 + i][j] = Feature[i * 8 + j];
+            }
+        }
+    }
+#endif
+#endif
+}
+#endif
+
+static void CopyFpuState(PCONTEXT Context, PFPU_STATE Fpu)
+{
+#if TG_ARCH == TG_ARCH_X86
+    if ((Context->ContextFlags & CONTEXT_EXTENDED_REGISTERS) == CONTEXT_EXTENDED_REGISTERS)
+    {
+        CopyLegacyState((const XSAVE_FORMAT*)Context->ExtendedRegisters, Fpu);
+        CopyExtendedState(Context, Fpu);
+    }
+#elif TG_ARCH == TG_ARCH_X64
+    if ((Context->ContextFlags & CONTEXT_FLOATING_POINT) == CONTEXT_FLOATING_POINT)
+    {
+        CopyLegacyState(&Context->FltSave, Fpu);
+        CopyExtendedState(Context, Fpu);
+    }
+#elif TG_ARCH == TG_ARCH_ARM64
+    if ((Context->ContextFlags & CONTEXT_FLOATING_POINT) == CONTEXT_FLOATING_POINT)
+    {
+        Fpu->Features = MS_FPU_NEON;
+        Fpu->Fpcr = Context->Fpcr;
+        Fpu->Fpsr = Context->Fpsr;
+
+        for (int i = 0; i < 32; ++i)
+        {
+            Fpu->V[i][0] = Context->V[i].Low;
+            Fpu->V[i][1] = (uint64_t)Context->V[i].High;
+        }
+    }
+#endif
+}
+
+void __microseh_CaptureContext(void* Context, PEXCEPTION Exception)
+{
+    CopyRegisters((PCONTEXT)Context, Exception->Registers);
+    CopyFpuState((PCONTEXT)Context, &Exception->Fpu);
+}
+
+void __microseh_ApplyContext(void* Context, const EXCEPTION* Exception)
+{
+    ApplyRegisters((PCONTEXT)Context, Exception->Registers);
+}
+#endif
+
+// Memory is read in chunks that never cross a page boundary, as pages are either readable or not.
+#define READ_CHUNK_SIZE 0x1000
+
+// Reads as many bytes as possible from the given address, without letting a failed read
+// raise another exception.
+static uint32_t ReadMemory(uintptr_t Address, uint8_t* Buffer, uint32_t Size)
+{
+    volatile uint32_t Length = 0;
+
+    while (Length < Size)
+    {
+        MEMORY_BASIC_INFORMATION Info;
+        uintptr_t Current = Address + Length;
+        uint32_t Chunk = READ_CHUNK_SIZE - (uint32_t)(Current & (READ_CHUNK_SIZE - 1));
+
+        if (Chunk > Size - Length)
+        {
+            Chunk = Size - Length;
+        }
+
+        // Touching a guard page would consume its guard, which is how thread stacks grow.
+        if (Current < Address ||
+            VirtualQuery((LPCVOID)Current, &Info, sizeof(Info)) == 0 ||
+            Info.State != MEM_COMMIT ||
+            (Info.Protect & (PAGE_GUARD | PAGE_NOACCESS)) != 0)
+        {
+            break;
+        }
+
+        __try
+        {
+            for (uint32_t i = 0; i < Chunk; ++i)
+            {
+                Buffer[Length + i] = ((const volatile uint8_t*)Current)[i];
+            }
+        }
+        __except (EXCEPTION_EXECUTE_HANDLER)
+        {
+            break;
+        }
+
+        Length += Chunk;
+    }
+
+    return Length;
+}
+
+void __microseh_CaptureMemory(PEXCEPTION Exception)
+{
+    Exception->Code.Address = Exception->Record.Address;
+    Exception->Code.Length = ReadMemory(Exception->Code.Address, Exception->Code.Bytes, MS_CODE_SNAPSHOT_SIZE);
+
+#if TG_ARCH != TG_ARCH_UNKNOWN
+    Exception->Stack.Address = Exception->Registers[MS_REG_SP];
+    Exception->Stack.Length = ReadMemory(Exception->Stack.Address, Exception->Stack.Bytes, MS_STACK_SNAPSHOT_SIZE);
+#endif
+
+    // Memory faults report the address of the data in their second parameter, and the window
+    // is centered on it, even though the bytes past it usually cannot be read.
+    switch (Exception->Record.Code)
+    {
+    case STATUS_ACCESS_VIOLATION:
+    case STATUS_IN_PAGE_ERROR:
+    case STATUS_GUARD_PAGE_VIOLATION:
+        if (Exception->Record.NumberParameters >= 2)
+        {
+            uintptr_t Data = Exception->Record.Information[1];
+
+            Exception->Data.Address = Data > MS_DATA_SNAPSHOT_SIZE / 2 ? Data - MS_DATA_SNAPSHOT_SIZE / 2 : 0;
+            Exception->Data.Length = ReadMemory(Exception->Data.Address, Exception->Data.Bytes, MS_DATA_SNAPSHOT_SIZE);
+        }
+        break;
+    }
+}
+
+void __microseh_RaiseException(const RECORD* Record)
+{
+    DWORD Count = Record->NumberParameters;
+    if (Count > MS_MAXIMUM_PARAMETERS)
+    {
+        Count = MS_MAXIMUM_PARAMETERS;
+    }
+
+    RaiseException(Record->Code, Record->Flags, Count, (const ULONG_PTR*)Record->Information);
+}
+
+void __microseh_RethrowException(const EXCEPTION* Exception)
+{
+    EXCEPTION_RECORD Record;
+    CONTEXT Context;
+    volatile LONG Raised = 0;
+
+    DWORD Count = Exception->Record.NumberParameters;
+    if (Count > MS_MAXIMUM_PARAMETERS)
+    {
+        Count = MS_MAXIMUM_PARAMETERS;
+    }
+
+    memset(&Record, 0, sizeof(Record));
+    Record.ExceptionCode = Exception->Record.Code;
+    Record.ExceptionFlags = Exception->Record.Flags;
+    Record.ExceptionAddress = (PVOID)Exception->Record.Address;
+    Record.NumberParameters = Count;
+    for (DWORD i = 0; i < Count; ++i)
+    {
+        Record.ExceptionInformation[i] = (ULONG_PTR)Exception->Record.Information[i];
+    }
+
+    // Execution resumes after this call if a filter continues the execution, with the context
+    // of this function, so the flag tells the two returns apart.
+    RtlCaptureContext(&Context);
+    if (!Raised)
+    {
+        Raised = 1;
+        NtRaiseException(&Record, &Context, TRUE);
+    }
+
+    // The context the exception occurred in is gone, so there is nothing to continue.
+    for (;;)
+    {
+        RaiseException(STATUS_NONCONTINUABLE_EXCEPTION, EXCEPTION_NONCONTINUABLE, 0, NULL);
+    }
+}
+
+// Call that is made on the stack of a fiber, and its result.
+typedef struct _STACK_CALL
+{
+    PPROC_EXECUTOR ProcExecutor;
+    void* Proc;
+    PEXCEPTION Exception;
+    uint32_t Result;
+    size_t HighWaterMark;
+    LPVOID Caller;
+} STACK_CALL, *PSTACK_CALL;
+
+static VOID WINAPI StackEntry(LPVOID Parameter)
+{
+    PSTACK_CALL Call = (PSTACK_CALL)Parameter;
+    PNT_TIB Tib = (PNT_TIB)NtCurrentTeb();
+
+    Call->Result = __microseh_HandlerStub(Call->ProcExecutor, Call->Proc, NULL, NULL, Call->Exception, 0);
+
+    // The stack is committed as it grows, which moves its limit down to the deepest page used.
+    Call->HighWaterMark = (uintptr_t)Tib->StackBase - (uintptr_t)Tib->StackLimit;
+
+    // A fiber must never return, as that would exit the thread.
+    SwitchToFiber(Call->Caller);
+}
+
+uint32_t __microseh_StackStub(
+    size_t Size,
+    PPROC_EXECUTOR ProcExecutor,
+    void* Proc,
+    PEXCEPTION Exception,
+    size_t* HighWaterMark
+) {
+    STACK_CALL Call;
+    LPVOID Fiber;
+    BOOL Converted = FALSE;
+
+    Call.ProcExecutor = ProcExecutor;
+    Call.Proc = Proc;
+    Call.Exception = Exception;
+    Call.Result = MS_STACK_UNAVAILABLE;
+    Call.HighWaterMark = 0;
+
+    // Only fibers can switch to other fibers, so the thread becomes one for the duration of the call.
+    if (!IsThreadAFiber())
+    {
+        if (ConvertThreadToFiber(NULL) == NULL)
+        {
+            return MS_STACK_UNAVAILABLE;
+        }
+
+        Converted = TRUE;
+    }
+
+    Call.Caller = GetCurrentFiber();
+    Fiber = CreateFiberEx(0, Size, FIBER_FLAG_FLOAT_SWITCH, StackEntry, &Call);
+    if (Fiber != NULL)
+    {
+        SwitchToFiber(Fiber);
+        DeleteFiber(Fiber);
+    }
+
+    if (Converted)
+    {
+        ConvertFiberToThread();
+    }
+
+    *HighWaterMark = Call.HighWaterMark;
+    return Call.Result;
+}